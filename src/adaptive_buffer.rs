@@ -0,0 +1,59 @@
+use std::{collections::VecDeque, sync::Mutex, time::Duration};
+
+/// The number of recent submissions to base the estimate on.
+const SAMPLE_COUNT: usize = 10;
+
+/// The buffer time to use before any submissions have been observed, in seconds.
+const INITIAL_BUFFER_TIME: u64 = 5;
+
+/// The bounds of the estimated buffer time, in seconds.
+const MIN_BUFFER_TIME: u64 = 1;
+const MAX_BUFFER_TIME: u64 = 30;
+
+struct Samples {
+    latencies: VecDeque<Duration>,
+    penalty: u64,
+}
+
+/// Estimates how long before the deadline to stop hashing, from how long recent submissions
+/// took to land.
+pub struct AdaptiveBuffer {
+    samples: Mutex<Samples>,
+}
+
+impl AdaptiveBuffer {
+    pub fn new() -> Self {
+        Self {
+            samples: Mutex::new(Samples {
+                latencies: VecDeque::with_capacity(SAMPLE_COUNT),
+                penalty: 0,
+            }),
+        }
+    }
+
+    /// Records how long a submission took to land, and whether it landed within the proof's
+    /// window. Late landings add a second of buffer until submissions land on time again.
+    pub fn record(&self, latency: Duration, landed_in_window: bool) {
+        let mut samples = self.samples.lock().unwrap();
+        if samples.latencies.len() == SAMPLE_COUNT {
+            samples.latencies.pop_front();
+        }
+        samples.latencies.push_back(latency);
+        if landed_in_window {
+            samples.penalty = samples.penalty.saturating_sub(1);
+        } else {
+            samples.penalty += 1;
+        }
+    }
+
+    /// The number of seconds before the deadline to stop hashing.
+    pub fn buffer_time(&self) -> u64 {
+        let samples = self.samples.lock().unwrap();
+        let Some(slowest) = samples.latencies.iter().max() else {
+            return INITIAL_BUFFER_TIME;
+        };
+        (slowest.as_secs_f64().ceil() as u64)
+            .saturating_add(samples.penalty)
+            .clamp(MIN_BUFFER_TIME, MAX_BUFFER_TIME)
+    }
+}
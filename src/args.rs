@@ -1,4 +1,15 @@
+use std::{net::SocketAddr, ops::Range};
+
 use clap::{arg, Parser};
+use ore_api::consts::MIN_DIFFICULTY;
+use solana_program::pubkey::Pubkey;
+
+use crate::{
+    bus_strategy::BusStrategy,
+    claim_split::ClaimSplit,
+    nonce_allocator::parse_nonce_range,
+    utils::{parse_amount, parse_amount_v1},
+};
 
 #[derive(Parser, Debug)]
 pub struct BalanceArgs {
@@ -30,9 +41,10 @@ pub struct ClaimArgs {
     #[arg(
         long,
         value_name = "AMOUNT",
-        help = "The amount of rewards to claim. Defaults to max."
+        help = "The amount of rewards to claim. Defaults to max.",
+        value_parser = parse_amount
     )]
-    pub amount: Option<f64>,
+    pub amount: Option<u64>,
 
     #[arg(
         long,
@@ -40,10 +52,43 @@ pub struct ClaimArgs {
         help = "Wallet to receive claimed tokens."
     )]
     pub to: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "ADDRESS:PERCENT,...",
+        help = "Split claimed tokens across wallets by percent, like addr1:60,addr2:40.",
+        conflicts_with = "to"
+    )]
+    pub split: Option<ClaimSplit>,
+}
+
+#[derive(Parser, Debug)]
+pub struct CloseArgs {
+    #[arg(
+        long,
+        value_name = "WALLET_ADDRESS",
+        help = "Wallet to receive claimed stake."
+    )]
+    pub to: Option<String>,
+
+    #[arg(
+        long,
+        help = "Also close your v1 and v2 ORE token accounts, if they are empty."
+    )]
+    pub token_accounts: bool,
 }
 
 #[derive(Parser, Debug)]
-pub struct CloseArgs {}
+pub struct CompoundArgs {
+    #[arg(
+        long,
+        value_name = "AMOUNT",
+        help = "Only stake once the wallet holds more than this amount of Ore.",
+        default_value = "0",
+        value_parser = parse_amount
+    )]
+    pub threshold: u64,
+}
 
 #[derive(Parser, Debug)]
 pub struct ConfigArgs {}
@@ -72,10 +117,135 @@ pub struct MineArgs {
         long,
         short,
         value_name = "SECONDS",
-        help = "The number seconds before the deadline to stop mining and start submitting",
-        default_value = "5"
+        help = "The number seconds before the deadline to stop mining and start submitting. Defaults to an estimate from recent landing times."
+    )]
+    pub buffer_time: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "START..END",
+        help = "The range of nonces to search, for splitting work across machines",
+        value_parser = parse_nonce_range,
+        conflicts_with = "worker_index"
+    )]
+    pub nonce_range: Option<Range<u64>>,
+
+    #[arg(
+        long,
+        value_name = "INDEX",
+        help = "The index of this machine when splitting nonces evenly across machines",
+        requires = "worker_count"
+    )]
+    pub worker_index: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "COUNT",
+        help = "The number of machines to split nonces evenly across",
+        requires = "worker_index",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub worker_count: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "DIFFICULTY",
+        help = "Submit as soon as any thread finds a hash of at least this difficulty"
+    )]
+    pub target_difficulty: Option<u32>,
+
+    #[arg(
+        long,
+        value_name = "DIFFICULTY",
+        help = "Keep mining past the deadline until a hash of at least this difficulty is found",
+        default_value_t = MIN_DIFFICULTY
+    )]
+    pub min_difficulty: u32,
+
+    #[arg(
+        long,
+        value_name = "STRATEGY",
+        help = "How to pick the bus to submit to: random, richest, least-contended, or fixed:N",
+        default_value = "random"
+    )]
+    pub bus_strategy: BusStrategy,
+
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        help = "The percent to raise the priority fee by each time an unlanded solution is resubmitted. An auto priority fee is raised no higher than --max-priority-fee",
+        default_value = "0"
+    )]
+    pub resubmit_fee_bump: u64,
+
+    #[arg(
+        long,
+        value_name = "DIR_OR_LIST",
+        help = "Mine with many keypairs at once, from a directory of keypair files or a comma separated list of keypair filepaths"
+    )]
+    pub keypairs: Option<String>,
+
+    #[arg(
+        long,
+        help = "Stake Ore from your wallet into your proof after each round, once above the compound threshold"
+    )]
+    pub compound: bool,
+
+    #[arg(
+        long,
+        value_name = "AMOUNT",
+        help = "Only compound once the wallet holds more than this amount of Ore",
+        default_value = "0",
+        value_parser = parse_amount
+    )]
+    pub compound_threshold: u64,
+
+    #[arg(
+        long,
+        value_name = "AMOUNT",
+        help = "Claim your stake after each round once it reaches this amount of Ore",
+        value_parser = parse_amount
+    )]
+    pub auto_claim_threshold: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "WALLET_ADDRESS",
+        help = "Wallet to receive auto-claimed tokens. Defaults to your wallet.",
+        requires = "auto_claim_threshold"
+    )]
+    pub auto_claim_to: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        help = "Serve Prometheus metrics on this address, such as 127.0.0.1:9100"
+    )]
+    pub metrics_addr: Option<SocketAddr>,
+
+    #[arg(
+        long,
+        conflicts_with = "keypairs",
+        help = "Show a live dashboard of hashrate, submissions, balances, and buses instead of the log"
+    )]
+    pub tui: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct OpenArgs {
+    #[arg(
+        long,
+        value_name = "MINER_ADDRESS",
+        help = "The address allowed to hash for the proof. Defaults to the keypair."
+    )]
+    pub miner: Option<Pubkey>,
+
+    #[arg(
+        long,
+        value_name = "KEYPAIR_FILEPATH",
+        help = "Filepath to keypair to pay the proof's rent with. Defaults to the fee payer."
     )]
-    pub buffer_time: u64,
+    pub payer: Option<String>,
 }
 
 #[derive(Parser, Debug)]
@@ -86,9 +256,10 @@ pub struct StakeArgs {
     #[arg(
         long,
         value_name = "AMOUNT",
-        help = "The amount of Ore to stake. Defaults to max."
+        help = "The amount of Ore to stake. Defaults to max.",
+        value_parser = parse_amount
     )]
-    pub amount: Option<f64>,
+    pub amount: Option<u64>,
 
     #[arg(
         long,
@@ -109,7 +280,8 @@ pub struct UpgradeArgs {
     #[arg(
         long,
         value_name = "AMOUNT",
-        help = "The amount of Ore to upgrade from v1 to v2. Defaults to max."
+        help = "The amount of Ore to upgrade from v1 to v2. Defaults to max.",
+        value_parser = parse_amount_v1
     )]
-    pub amount: Option<f64>,
+    pub amount: Option<u64>,
 }
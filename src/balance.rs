@@ -1,45 +1,62 @@
-use std::str::FromStr;
-
-use solana_program::pubkey::Pubkey;
-use solana_sdk::signature::Signer;
+use serde::Serialize;
 
 use crate::{
     args::BalanceArgs,
-    utils::{amount_u64_to_string, get_proof_with_authority},
+    error::Result,
+    output::{Report, TokenAmount},
+    utils::{get_proof_with_authority, get_token_balance, parse_pubkey},
     Miner,
 };
 
+#[derive(Serialize)]
+pub struct BalanceReport {
+    pub address: String,
+    pub balance: TokenAmount,
+    pub stake: TokenAmount,
+}
+
+impl Report for BalanceReport {
+    fn text(&self) -> String {
+        format!(
+            "Balance: {} ORE\nStake: {} ORE",
+            self.balance.ui_amount, self.stake.ui_amount
+        )
+    }
+
+    fn csv_header(&self) -> Vec<&'static str> {
+        vec!["address", "balance", "balance_ui", "stake", "stake_ui"]
+    }
+
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        vec![vec![
+            self.address.clone(),
+            self.balance.amount.to_string(),
+            self.balance.ui_amount.clone(),
+            self.stake.amount.to_string(),
+            self.stake.ui_amount.clone(),
+        ]]
+    }
+}
+
 impl Miner {
-    pub async fn balance(&self, args: BalanceArgs) {
-        let signer = self.signer();
+    pub async fn balance(&self, args: BalanceArgs) -> Result<()> {
         let address = if let Some(address) = args.address {
-            if let Ok(address) = Pubkey::from_str(&address) {
-                address
-            } else {
-                println!("Invalid address: {:?}", address);
-                return;
-            }
+            parse_pubkey(&address)?
         } else {
-            signer.pubkey()
+            self.authority()
         };
-        let proof = get_proof_with_authority(&self.rpc_client, address).await;
+        let proof = get_proof_with_authority(&self.rpc_client, address).await?;
         let token_account_address = spl_associated_token_account::get_associated_token_address(
             &address,
             &ore_api::consts::MINT_ADDRESS,
         );
-        let token_balance = if let Ok(Some(token_account)) = self
-            .rpc_client
-            .get_token_account(&token_account_address)
-            .await
-        {
-            token_account.token_amount.ui_amount_string
-        } else {
-            "0".to_string()
-        };
-        println!(
-            "Balance: {} ORE\nStake: {} ORE",
-            token_balance,
-            amount_u64_to_string(proof.balance)
-        )
+        let token_balance = get_token_balance(&self.rpc_client, &token_account_address)
+            .await?
+            .unwrap_or(0);
+        self.print_report(&BalanceReport {
+            address: address.to_string(),
+            balance: TokenAmount::new(token_balance),
+            stake: TokenAmount::new(proof.balance),
+        })
     }
 }
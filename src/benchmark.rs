@@ -1,52 +1,83 @@
 use std::{sync::Arc, time::Instant};
 
+use serde::Serialize;
 use solana_rpc_client::spinner;
 
-use crate::{args::BenchmarkArgs, Miner};
+use crate::{
+    args::BenchmarkArgs, error::Result, nonce_allocator::NonceAllocator, output::Report, Miner,
+};
 
 const TEST_DURATION: i64 = 30;
 
+#[derive(Serialize)]
+pub struct BenchmarkReport {
+    pub threads: u64,
+    pub duration: u64,
+    pub hashes: u64,
+    pub hashrate: u64,
+}
+
+impl Report for BenchmarkReport {
+    fn text(&self) -> String {
+        format!("Hashpower: {} H/sec", self.hashrate)
+    }
+
+    fn csv_header(&self) -> Vec<&'static str> {
+        vec!["threads", "duration", "hashes", "hashrate"]
+    }
+
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        vec![vec![
+            self.threads.to_string(),
+            self.duration.to_string(),
+            self.hashes.to_string(),
+            self.hashrate.to_string(),
+        ]]
+    }
+}
+
 impl Miner {
-    pub async fn benchmark(&self, args: BenchmarkArgs) {
+    pub async fn benchmark(&self, args: BenchmarkArgs) -> Result<()> {
         // Check num threads
         self.check_num_cores(args.threads);
 
         // Dispatch job to each thread
         let challenge = [0; 32];
+        let nonces = Arc::new(NonceAllocator::new(0..u64::MAX));
         let progress_bar = Arc::new(spinner::new_progress_bar());
         progress_bar.set_message(format!(
             "Benchmarking. This will take {} sec...",
             TEST_DURATION
         ));
         let handles: Vec<_> = (0..args.threads)
-            .map(|i| {
+            .map(|_| {
                 std::thread::spawn({
+                    let nonces = nonces.clone();
                     move || {
                         let timer = Instant::now();
-                        let first_nonce = u64::MAX.saturating_div(args.threads).saturating_mul(i);
-                        let mut nonce = first_nonce;
-                        loop {
-                            // Create hash
-                            let _hx = drillx::hash(&challenge, &nonce.to_le_bytes());
-
-                            // Increment nonce
-                            nonce += 1;
-
-                            // Exit if time has elapsed
-                            if (timer.elapsed().as_secs() as i64).ge(&TEST_DURATION) {
-                                break;
+                        let mut hashes = 0u64;
+                        while let Some(chunk) = nonces.next_chunk() {
+                            for nonce in chunk {
+                                // Create hash
+                                let _hx = drillx::hash(&challenge, &nonce.to_le_bytes());
+                                hashes += 1;
+
+                                // Exit if time has elapsed
+                                if (timer.elapsed().as_secs() as i64).ge(&TEST_DURATION) {
+                                    return hashes;
+                                }
                             }
                         }
 
                         // Return hash count
-                        nonce - first_nonce
+                        hashes
                     }
                 })
             })
             .collect();
 
         // Join handles and return best nonce
-        let mut total_nonces = 0;
+        let mut total_nonces = 0u64;
         for h in handles {
             if let Ok(count) = h.join() {
                 total_nonces += count;
@@ -54,9 +85,12 @@ impl Miner {
         }
 
         // Update log
-        progress_bar.finish_with_message(format!(
-            "Hashpower: {} H/sec",
-            total_nonces.saturating_div(TEST_DURATION as u64),
-        ));
+        progress_bar.finish_and_clear();
+        self.print_report(&BenchmarkReport {
+            threads: args.threads,
+            duration: TEST_DURATION as u64,
+            hashes: total_nonces,
+            hashrate: total_nonces.saturating_div(TEST_DURATION as u64),
+        })
     }
 }
@@ -0,0 +1,151 @@
+use std::str::FromStr;
+
+use futures::future::join_all;
+use ore_api::{
+    consts::{BUS_ADDRESSES, BUS_COUNT, BUS_EPOCH_REWARDS, MIN_DIFFICULTY, ONE_MINUTE},
+    state::{Bus, Config, Proof},
+};
+use ore_utils::AccountDeserialize;
+use rand::seq::SliceRandom;
+
+use crate::{
+    error::{OreCliError, Result},
+    Miner,
+};
+
+#[derive(Clone, Copy, Debug)]
+pub enum BusStrategy {
+    /// Pick any bus at random.
+    Random,
+
+    /// Pick the bus with the most rewards left.
+    Richest,
+
+    /// Pick the bus with the lowest recent priority fees.
+    LeastContended,
+
+    /// Always pick the given bus, unless it cannot cover the reward.
+    Fixed(usize),
+}
+
+impl FromStr for BusStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "random" => Ok(BusStrategy::Random),
+            "richest" => Ok(BusStrategy::Richest),
+            "least-contended" => Ok(BusStrategy::LeastContended),
+            _ => {
+                let id = s
+                    .strip_prefix("fixed:")
+                    .and_then(|id| usize::from_str(id).ok())
+                    .ok_or_else(|| {
+                        format!(
+                            "expected random, richest, least-contended, or fixed:N, got {}",
+                            s
+                        )
+                    })?;
+                if id >= BUS_COUNT {
+                    return Err(format!(
+                        "bus id must be less than {}, got {}",
+                        BUS_COUNT, id
+                    ));
+                }
+                Ok(BusStrategy::Fixed(id))
+            }
+        }
+    }
+}
+
+impl Miner {
+    /// Picks the bus to submit a hash to. If the transaction resets the epoch first, every bus
+    /// is refilled before the hash is paid out, so the current balances are ignored.
+    pub async fn find_bus(
+        &self,
+        strategy: BusStrategy,
+        reward: u64,
+        resetting: bool,
+    ) -> Result<usize> {
+        // Fetch all buses
+        let accounts = self
+            .rpc_client
+            .get_multiple_accounts(&BUS_ADDRESSES)
+            .await?;
+        let mut buses = vec![];
+        for account in accounts.into_iter().flatten() {
+            let mut bus = *Bus::try_from_bytes(&account.data)
+                .map_err(|_| OreCliError::Parse("Failed to parse bus account".into()))?;
+            if resetting {
+                bus.rewards = BUS_EPOCH_REWARDS;
+            }
+            buses.push(bus);
+        }
+
+        // Prefer buses that can cover the expected reward
+        let mut candidates: Vec<Bus> = buses
+            .iter()
+            .filter(|bus| bus.rewards.ge(&reward))
+            .copied()
+            .collect();
+        if candidates.is_empty() {
+            candidates = buses;
+        }
+        let richest = candidates.iter().max_by_key(|bus| bus.rewards).copied();
+
+        // Apply strategy
+        let bus = match strategy {
+            BusStrategy::Random => candidates.choose(&mut rand::thread_rng()).copied(),
+            BusStrategy::Richest => richest,
+            BusStrategy::LeastContended => {
+                let fees = join_all(candidates.iter().map(|bus| {
+                    let address = BUS_ADDRESSES[bus.id as usize];
+                    async move {
+                        self.rpc_client
+                            .get_recent_prioritization_fees(&[address])
+                            .await
+                    }
+                }))
+                .await;
+                candidates
+                    .iter()
+                    .zip(fees)
+                    .min_by_key(|(_, fees)| match fees {
+                        Ok(fees) if !fees.is_empty() => {
+                            fees.iter().map(|f| f.prioritization_fee).sum::<u64>()
+                                / fees.len() as u64
+                        }
+                        _ => u64::MAX,
+                    })
+                    .map(|(bus, _)| *bus)
+            }
+            BusStrategy::Fixed(id) => candidates
+                .iter()
+                .find(|bus| bus.id as usize == id)
+                .copied()
+                .or(richest),
+        };
+        bus.map(|bus| bus.id as usize)
+            .ok_or_else(|| OreCliError::Parse("No bus available".into()))
+    }
+}
+
+/// The reward a hash of the given difficulty earns at the current base reward rate, including
+/// the stake multiplier. A proof's stake raises its reward by up to 2x in proportion to the max
+/// stake, once its last stake is more than a minute old.
+pub fn expected_reward(config: Config, proof: Proof, difficulty: u32, now: i64) -> u64 {
+    let reward = config
+        .base_reward_rate
+        .saturating_mul(2u64.saturating_pow(difficulty.saturating_sub(MIN_DIFFICULTY)));
+    if proof.balance.eq(&0)
+        || config.max_stake.eq(&0)
+        || proof.last_stake_at.saturating_add(ONE_MINUTE).ge(&now)
+    {
+        return reward;
+    }
+    let staking_reward = (reward as u128)
+        .saturating_mul(proof.balance.min(config.max_stake) as u128)
+        .checked_div(config.max_stake as u128)
+        .unwrap_or(0) as u64;
+    reward.saturating_add(staking_reward)
+}
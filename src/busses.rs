@@ -1,23 +1,65 @@
-use ore_api::{
-    consts::{BUS_ADDRESSES, TOKEN_DECIMALS},
-    state::Bus,
-};
+use ore_api::{consts::BUS_ADDRESSES, state::Bus};
 use ore_utils::AccountDeserialize;
+use serde::Serialize;
+
+use crate::{
+    error::Result,
+    output::{Report, TokenAmount},
+    Miner,
+};
+
+#[derive(Serialize)]
+pub struct BusReport {
+    pub id: u64,
+    pub address: String,
+    pub rewards: TokenAmount,
+}
+
+#[derive(Serialize)]
+pub struct BussesReport(pub Vec<BusReport>);
 
-use crate::Miner;
+impl Report for BussesReport {
+    fn text(&self) -> String {
+        self.0
+            .iter()
+            .map(|bus| format!("Bus {}: {} ORE", bus.id, bus.rewards.ui_amount))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn csv_header(&self) -> Vec<&'static str> {
+        vec!["id", "address", "rewards", "rewards_ui"]
+    }
+
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        self.0
+            .iter()
+            .map(|bus| {
+                vec![
+                    bus.id.to_string(),
+                    bus.address.clone(),
+                    bus.rewards.amount.to_string(),
+                    bus.rewards.ui_amount.clone(),
+                ]
+            })
+            .collect()
+    }
+}
 
 impl Miner {
-    pub async fn busses(&self) {
+    pub async fn busses(&self) -> Result<()> {
         let client = self.rpc_client.clone();
+        let mut busses = vec![];
         for address in BUS_ADDRESSES.iter() {
-            let data = client.get_account_data(address).await.unwrap();
-            match Bus::try_from_bytes(&data) {
-                Ok(bus) => {
-                    let rewards = (bus.rewards as f64) / 10f64.powf(TOKEN_DECIMALS as f64);
-                    println!("Bus {}: {:} ORE", bus.id, rewards);
-                }
-                Err(_) => {}
+            let data = client.get_account_data(address).await?;
+            if let Ok(bus) = Bus::try_from_bytes(&data) {
+                busses.push(BusReport {
+                    id: bus.id,
+                    address: address.to_string(),
+                    rewards: TokenAmount::new(bus.rewards),
+                });
             }
         }
+        self.print_report(&BussesReport(busses))
     }
 }
@@ -0,0 +1,114 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use colored::*;
+use solana_client::nonblocking::rpc_client::RpcClient;
+
+use crate::{dashboard::Dashboard, error::Result, utils::get_clock};
+
+/// How often to resample the chain clock, in seconds.
+const SAMPLE_INTERVAL: u64 = 30;
+
+/// The weight of a new sample in the smoothed offset.
+const SMOOTHING: f64 = 0.25;
+
+/// The offset between local and chain time above which to warn, in seconds.
+const DRIFT_WARNING: f64 = 5.0;
+
+#[derive(Default)]
+struct Offset {
+    seconds: f64,
+    sampled_at: Option<Instant>,
+}
+
+/// Tracks the offset between the local clock and the chain clock, so time-sensitive decisions
+/// can use an estimate of the chain's current time without an rpc round trip each time.
+pub struct ChainClock {
+    rpc_client: Arc<RpcClient>,
+    dashboard: Arc<Dashboard>,
+    offset: Mutex<Offset>,
+}
+
+impl ChainClock {
+    pub fn new(rpc_client: Arc<RpcClient>, dashboard: Arc<Dashboard>) -> Self {
+        Self {
+            rpc_client,
+            dashboard,
+            offset: Mutex::new(Offset::default()),
+        }
+    }
+
+    /// The estimated current unix timestamp of the chain.
+    pub async fn now(&self) -> Result<i64> {
+        let stale = self.offset.lock().unwrap().sampled_at.map_or(true, |t| {
+            t.elapsed().ge(&Duration::from_secs(SAMPLE_INTERVAL))
+        });
+        if stale {
+            self.sample().await?;
+        }
+        let offset = self.offset.lock().unwrap().seconds;
+        Ok((local_now() + offset).floor() as i64)
+    }
+
+    /// Samples the clock sysvar, whose timestamp is the stake-weighted time of the latest slot,
+    /// along with the block time of the latest slot, and folds the observed offset into the
+    /// estimate.
+    async fn sample(&self) -> Result<()> {
+        // Assume the clock was read halfway through the round trip
+        let before = local_now();
+        let clock = get_clock(&self.rpc_client).await?;
+        let after = local_now();
+        let mut sample = clock.unix_timestamp as f64 - (before + after) / 2.0;
+
+        // Both readings trail the chain, so keep whichever is fresher
+        if let Some(slot_sample) = self.sample_slot_time().await {
+            sample = sample.max(slot_sample);
+        }
+
+        // Smooth out the one second resolution of the chain clock
+        let mut offset = self.offset.lock().unwrap();
+        offset.seconds = match offset.sampled_at {
+            Some(_) => offset.seconds + SMOOTHING * (sample - offset.seconds),
+            None => sample,
+        };
+        offset.sampled_at = Some(Instant::now());
+        if offset.seconds.abs().gt(&DRIFT_WARNING) {
+            self.dashboard.print(format!(
+                "{} Local clock is {:.1} sec {} chain time",
+                "WARNING".bold().yellow(),
+                offset.seconds.abs(),
+                if offset.seconds.gt(&0.0) {
+                    "behind"
+                } else {
+                    "ahead of"
+                }
+            ));
+        }
+        Ok(())
+    }
+
+    /// Samples the block time of the latest slot. The block of the newest slot may not be
+    /// available yet, in which case there is no sample.
+    async fn sample_slot_time(&self) -> Option<f64> {
+        let before = local_now();
+        let slot = self.rpc_client.get_slot().await.ok()?;
+        let block_time = match self.rpc_client.get_block_time(slot).await {
+            Ok(block_time) => block_time,
+            Err(err) => {
+                tracing::debug!(slot, %err, "Failed to read block time");
+                return None;
+            }
+        };
+        let after = local_now();
+        Some(block_time as f64 - (before + after) / 2.0)
+    }
+}
+
+fn local_now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+}
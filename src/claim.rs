@@ -1,83 +1,116 @@
-use std::str::FromStr;
-
 use colored::*;
 use ore_api::consts::MINT_ADDRESS;
-use solana_program::pubkey::Pubkey;
-use solana_sdk::signature::Signer;
-use spl_token::amount_to_ui_amount;
+use solana_program::{instruction::Instruction, pubkey::Pubkey};
+use solana_sdk::signature::{Signature, Signer};
 
 use crate::{
-    args::ClaimArgs,
+    args::{ClaimArgs, MineArgs},
     cu_limits::CU_LIMIT_CLAIM,
+    error::{OreCliError, Result},
     send_and_confirm::ComputeBudget,
-    utils::{amount_f64_to_u64, ask_confirm, get_proof_with_authority},
+    utils::{
+        amount_u64_to_string, ask_confirm, get_proof_with_authority, get_token_balance,
+        parse_pubkey,
+    },
     Miner,
 };
 
 impl Miner {
-    pub async fn claim(&self, args: ClaimArgs) {
+    pub async fn claim(&self, args: ClaimArgs) -> Result<()> {
         let signer = self.signer();
         let pubkey = signer.pubkey();
-        let proof = get_proof_with_authority(&self.rpc_client, pubkey).await;
-        let mut ixs = vec![];
-        let beneficiary = match args.to {
-            Some(to) => {
-                // Create beneficiary token account, if needed
-                let wallet = Pubkey::from_str(&to).expect("Failed to parse wallet address");
-                let benefiary_tokens = spl_associated_token_account::get_associated_token_address(
-                    &wallet,
-                    &MINT_ADDRESS,
-                );
-                if self
-                    .rpc_client
-                    .get_token_account(&benefiary_tokens)
-                    .await
-                    .is_err()
-                {
-                    ixs.push(
-                        spl_associated_token_account::instruction::create_associated_token_account(
-                            &signer.pubkey(),
-                            &wallet,
-                            &ore_api::consts::MINT_ADDRESS,
-                            &spl_token::id(),
-                        ),
-                    );
-                }
-                benefiary_tokens
-            }
-            None => self.initialize_ata().await,
-        };
+        let proof = get_proof_with_authority(&self.rpc_client, pubkey).await?;
+        let wallet = args.to.as_deref().map(parse_pubkey).transpose()?;
 
-        // Parse amount to claim
-        let amount = if let Some(amount) = args.amount {
-            amount_f64_to_u64(amount)
-        } else {
-            proof.balance
-        };
+        // Default to claiming the whole balance
+        let amount = args.amount.unwrap_or(proof.balance);
+
+        // Split the claim across beneficiaries, if requested
+        if let Some(split) = args.split {
+            return self.claim_split(split, amount).await;
+        }
 
         // Confirm user wants to claim
         if !ask_confirm(
             format!(
                 "\nYou are about to claim {}.\n\nAre you sure you want to continue? [Y/n]",
-                format!(
-                    "{} ORE",
-                    amount_to_ui_amount(amount, ore_api::consts::TOKEN_DECIMALS)
-                )
-                .bold(),
+                format!("{} ORE", amount_u64_to_string(amount)).bold(),
             )
             .as_str(),
+            self.assume_yes,
         ) {
-            return;
+            return Err(OreCliError::UserAbort);
         }
 
         // Send and confirm
+        self.claim_to(wallet, amount).await?;
+        Ok(())
+    }
+
+    /// Claims from the proof to a wallet, or the signer's own, creating its token account if
+    /// needed.
+    pub async fn claim_to(&self, wallet: Option<Pubkey>, amount: u64) -> Result<Signature> {
+        let signer = self.signer();
+        let pubkey = signer.pubkey();
+        let mut ixs = vec![];
+        let beneficiary = match wallet {
+            Some(wallet) => self.beneficiary_tokens(wallet, &mut ixs).await?,
+            None => self.initialize_ata().await?,
+        };
         ixs.push(ore_api::instruction::claim(pubkey, beneficiary, amount));
         self.send_and_confirm(&ixs, ComputeBudget::Fixed(CU_LIMIT_CLAIM), false)
             .await
-            .ok();
     }
 
-    async fn initialize_ata(&self) -> Pubkey {
+    /// Returns a wallet's ORE token account, adding an instruction to create it if needed.
+    pub async fn beneficiary_tokens(
+        &self,
+        wallet: Pubkey,
+        ixs: &mut Vec<Instruction>,
+    ) -> Result<Pubkey> {
+        let benefiary_tokens =
+            spl_associated_token_account::get_associated_token_address(&wallet, &MINT_ADDRESS);
+        if get_token_balance(&self.rpc_client, &benefiary_tokens)
+            .await?
+            .is_none()
+        {
+            ixs.push(
+                spl_associated_token_account::instruction::create_associated_token_account(
+                    &self.fee_payer().pubkey(),
+                    &wallet,
+                    &ore_api::consts::MINT_ADDRESS,
+                    &spl_token::id(),
+                ),
+            );
+        }
+        Ok(benefiary_tokens)
+    }
+
+    /// Claims the proof balance after a mining round, if enabled and above the threshold.
+    pub async fn auto_claim_after_round(&self, args: &MineArgs) -> Result<()> {
+        let Some(threshold) = args.auto_claim_threshold else {
+            return Ok(());
+        };
+        let proof = get_proof_with_authority(&self.rpc_client, self.signer().pubkey()).await?;
+        if proof.balance.lt(&threshold) || proof.balance.eq(&0) {
+            return Ok(());
+        }
+        let wallet = args
+            .auto_claim_to
+            .as_deref()
+            .map(parse_pubkey)
+            .transpose()?;
+        let sig = self.claim_to(wallet, proof.balance).await?;
+        self.print(format!(
+            "Auto-claimed {} ORE to {} ({})",
+            amount_u64_to_string(proof.balance),
+            wallet.unwrap_or(self.signer().pubkey()),
+            sig
+        ));
+        Ok(())
+    }
+
+    async fn initialize_ata(&self) -> Result<Pubkey> {
         // Initialize client.
         let signer = self.signer();
         let client = self.rpc_client.clone();
@@ -89,21 +122,23 @@ impl Miner {
         );
 
         // Check if ata already exists
-        if let Ok(Some(_ata)) = client.get_token_account(&token_account_pubkey).await {
-            return token_account_pubkey;
+        if get_token_balance(&client, &token_account_pubkey)
+            .await?
+            .is_some()
+        {
+            return Ok(token_account_pubkey);
         }
         // Sign and send transaction.
         let ix = spl_associated_token_account::instruction::create_associated_token_account(
-            &signer.pubkey(),
+            &self.fee_payer().pubkey(),
             &signer.pubkey(),
             &ore_api::consts::MINT_ADDRESS,
             &spl_token::id(),
         );
         self.send_and_confirm(&[ix], ComputeBudget::Dynamic, false)
-            .await
-            .ok();
+            .await?;
 
         // Return token account address
-        token_account_pubkey
+        Ok(token_account_pubkey)
     }
 }
@@ -0,0 +1,211 @@
+use std::str::FromStr;
+
+use colored::*;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+
+use crate::{
+    error::{OreCliError, Result},
+    send_and_confirm::ComputeBudget,
+    utils::{amount_u64_to_string, ask_confirm},
+    Miner,
+};
+
+/// The most beneficiaries to claim to in one transaction, so it stays under the size limit.
+const SPLIT_BATCH_SIZE: usize = 5;
+
+/// Beneficiaries of a claim, each with a whole percent share.
+#[derive(Clone, Debug)]
+pub struct ClaimSplit {
+    pub shares: Vec<(Pubkey, u64)>,
+}
+
+impl FromStr for ClaimSplit {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut shares: Vec<(Pubkey, u64)> = vec![];
+        for part in s.split(',') {
+            let (address, percent) = part
+                .trim()
+                .split_once(':')
+                .ok_or_else(|| format!("expected ADDRESS:PERCENT, got {}", part))?;
+            let address =
+                Pubkey::from_str(address).map_err(|_| format!("invalid address: {}", address))?;
+            let percent = percent
+                .parse::<u64>()
+                .map_err(|_| format!("invalid percent: {}", percent))?;
+            if percent == 0 {
+                return Err(format!("percent for {} must be more than 0", address));
+            }
+            if shares.iter().any(|(a, _)| a.eq(&address)) {
+                return Err(format!("duplicate address: {}", address));
+            }
+            shares.push((address, percent));
+        }
+        let total = shares.iter().map(|(_, percent)| percent).sum::<u64>();
+        if total != 100 {
+            return Err(format!("percents must add up to 100, got {}", total));
+        }
+        Ok(ClaimSplit { shares })
+    }
+}
+
+impl ClaimSplit {
+    /// Splits an amount by share. Each beneficiary gets the floor of its share, and the units
+    /// left over go one each to the largest remainders, so the amounts add up to exactly the
+    /// total.
+    pub fn amounts(&self, total: u64) -> Vec<(Pubkey, u64)> {
+        let mut amounts: Vec<(Pubkey, u64, u128)> = self
+            .shares
+            .iter()
+            .map(|(address, percent)| {
+                let exact = total as u128 * *percent as u128;
+                (*address, (exact / 100) as u64, exact % 100)
+            })
+            .collect();
+        let mut leftover = total - amounts.iter().map(|(_, amount, _)| amount).sum::<u64>();
+        let mut order: Vec<usize> = (0..amounts.len()).collect();
+        order.sort_by(|a, b| amounts[*b].2.cmp(&amounts[*a].2));
+        for i in order {
+            if leftover == 0 {
+                break;
+            }
+            amounts[i].1 += 1;
+            leftover -= 1;
+        }
+        amounts
+            .into_iter()
+            .map(|(address, amount, _)| (address, amount))
+            .collect()
+    }
+}
+
+impl Miner {
+    /// Claims an amount split across beneficiaries, with as few transactions as fit.
+    pub async fn claim_split(&self, split: ClaimSplit, amount: u64) -> Result<()> {
+        // Confirm the distribution
+        let amounts = split.amounts(amount);
+        let mut distribution = String::new();
+        for ((address, amount), (_, percent)) in amounts.iter().zip(split.shares.iter()) {
+            distribution.push_str(&format!(
+                "\n  {} {}% {} ORE",
+                address,
+                percent,
+                amount_u64_to_string(*amount)
+            ));
+        }
+        if !ask_confirm(
+            format!(
+                "\nYou are about to claim {}, split as:{}\n\nAre you sure you want to continue? [Y/n]",
+                format!("{} ORE", amount_u64_to_string(amount)).bold(),
+                distribution
+            )
+            .as_str(),
+            self.assume_yes,
+        ) {
+            return Err(OreCliError::UserAbort);
+        }
+
+        // Send claims in batches that fit in a transaction
+        let signer = self.signer();
+        for batch in amounts.chunks(SPLIT_BATCH_SIZE) {
+            let mut ixs = vec![];
+            for (wallet, amount) in batch {
+                if amount.eq(&0) {
+                    continue;
+                }
+                let beneficiary = self.beneficiary_tokens(*wallet, &mut ixs).await?;
+                ixs.push(ore_api::instruction::claim(
+                    signer.pubkey(),
+                    beneficiary,
+                    *amount,
+                ));
+            }
+            if !ixs.is_empty() {
+                self.send_and_confirm(&ixs, ComputeBudget::Dynamic, false)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn split(percents: &[u64]) -> ClaimSplit {
+        ClaimSplit {
+            shares: percents
+                .iter()
+                .map(|percent| (Pubkey::new_unique(), *percent))
+                .collect(),
+        }
+    }
+
+    fn total(amounts: &[(Pubkey, u64)]) -> u128 {
+        amounts.iter().map(|(_, amount)| *amount as u128).sum()
+    }
+
+    #[test]
+    fn parses_shares() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let split = ClaimSplit::from_str(&format!("{}:60, {}:40", a, b)).unwrap();
+        assert_eq!(split.shares, vec![(a, 60), (b, 40)]);
+    }
+
+    #[test]
+    fn rejects_bad_shares() {
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        for s in [
+            format!("{}:60,{}:30", a, b),
+            format!("{}:60,{}:50", a, b),
+            format!("{}:50.5,{}:49.5", a, b),
+            format!("{}:50,{}:50", a, a),
+            format!("{}:100,{}:0", a, b),
+            format!("{}", a),
+            "nope:100".to_string(),
+        ] {
+            assert!(ClaimSplit::from_str(&s).is_err(), "accepted {}", s);
+        }
+    }
+
+    #[test]
+    fn amounts_of_small_totals() {
+        let split = split(&[33, 33, 34]);
+        for amount in 0..=3 {
+            assert_eq!(total(&split.amounts(amount)), amount as u128);
+        }
+        let amounts: Vec<u64> = split.amounts(1).iter().map(|(_, a)| *a).collect();
+        assert_eq!(amounts, vec![0, 0, 1]);
+    }
+
+    #[test]
+    fn amounts_near_max() {
+        for percents in [&[100][..], &[50, 50], &[33, 33, 34], &[1, 99]] {
+            for amount in [u64::MAX, u64::MAX - 1, u64::MAX / 3] {
+                assert_eq!(total(&split(percents).amounts(amount)), amount as u128);
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn amounts_add_up(
+            percents in prop::collection::vec(1u64..=10, 0..10),
+            amount: u64,
+        ) {
+            // Top up with a last share so the percents add up to 100
+            let mut percents = percents;
+            percents.push(100 - percents.iter().sum::<u64>());
+            let amounts = split(&percents).amounts(amount);
+            prop_assert_eq!(total(&amounts), amount as u128);
+            for ((_, amount_out), percent) in amounts.iter().zip(percents) {
+                let exact = amount as u128 * percent as u128 / 100;
+                prop_assert!(*amount_out as u128 - exact <= 1);
+            }
+        }
+    }
+}
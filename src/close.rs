@@ -1,44 +1,147 @@
 use colored::*;
+use ore_api::consts::{MINT_ADDRESS, MINT_V1_ADDRESS};
+use solana_program::{native_token::lamports_to_sol, pubkey::Pubkey};
 use solana_sdk::signature::Signer;
-use spl_token::amount_to_ui_amount;
 
 use crate::{
-    args::ClaimArgs,
+    args::CloseArgs,
+    error::{OreCliError, Result},
     send_and_confirm::ComputeBudget,
-    utils::{ask_confirm, get_proof},
+    utils::{
+        amount_u64_to_string, ask_confirm, get_proof, get_token_balance, parse_pubkey, proof_pubkey,
+    },
     Miner,
 };
 
 impl Miner {
-    pub async fn close(&self) {
+    pub async fn close(&self, args: CloseArgs) -> Result<()> {
         // Confirm proof exists
         let signer = self.signer();
-        let proof = get_proof(&self.rpc_client, signer.pubkey()).await;
+        let proof_address = proof_pubkey(signer.pubkey());
+        let proof = get_proof(&self.rpc_client, proof_address).await?;
+        let proof_lamports = self.rpc_client.get_balance(&proof_address).await?;
+
+        // Check the claim destination before previewing
+        let to = args.to.as_deref().map(parse_pubkey).transpose()?;
+
+        // Find empty token accounts to close, skipping the one the stake is claimed to
+        let claims_to_ata =
+            proof.balance.gt(&0) && to.unwrap_or(signer.pubkey()).eq(&signer.pubkey());
+        let mut token_accounts = vec![];
+        if args.token_accounts {
+            for (name, mint) in [("v1", MINT_V1_ADDRESS), ("v2", MINT_ADDRESS)] {
+                if mint.eq(&MINT_ADDRESS) && claims_to_ata {
+                    continue;
+                }
+                let address = spl_associated_token_account::get_associated_token_address(
+                    &signer.pubkey(),
+                    &mint,
+                );
+                if let Some(lamports) = self.empty_token_account_lamports(&address).await? {
+                    token_accounts.push((name, address, lamports));
+                }
+            }
+        }
+
+        // Build the close transaction
+        let mut close_ixs = vec![ore_api::instruction::close(signer.pubkey())];
+        for (_, address, _) in token_accounts.iter() {
+            close_ixs.push(
+                spl_token::instruction::close_account(
+                    &spl_token::id(),
+                    address,
+                    &signer.pubkey(),
+                    &signer.pubkey(),
+                    &[],
+                )
+                .map_err(|err| OreCliError::Parse(err.to_string()))?,
+            );
+        }
+
+        // Simulate the claim and close together, so a close that would fail stops before asking
+        let mut sim_ixs = vec![];
+        if proof.balance.gt(&0) {
+            let beneficiary = self
+                .beneficiary_tokens(to.unwrap_or(signer.pubkey()), &mut sim_ixs)
+                .await?;
+            sim_ixs.push(ore_api::instruction::claim(
+                signer.pubkey(),
+                beneficiary,
+                proof.balance,
+            ));
+        }
+        sim_ixs.extend_from_slice(&close_ixs);
+        if let Err(err) = self
+            .simulate_compute_units(&sim_ixs, 0, &self.fee_payer().pubkey())
+            .await
+        {
+            println!("Simulating the claim and close failed, nothing was sent.");
+            return Err(err);
+        }
+
+        // Preview what closing will do
+        let reclaimable = token_accounts
+            .iter()
+            .fold(proof_lamports, |total, (_, _, lamports)| total + lamports);
+        println!("{}", "Close preview".bold());
+        if proof.balance.gt(&0) {
+            println!(
+                "  Claim {} ORE of stake to {}",
+                amount_u64_to_string(proof.balance),
+                to.map_or("your wallet".to_string(), |to| to.to_string())
+            );
+        }
+        println!(
+            "  Close proof {} ({} SOL)",
+            proof_address,
+            lamports_to_sol(proof_lamports)
+        );
+        for (name, address, lamports) in token_accounts.iter() {
+            println!(
+                "  Close empty {} token account {} ({} SOL)",
+                name,
+                address,
+                lamports_to_sol(*lamports)
+            );
+        }
+        println!(
+            "  Reclaim {} lamports ({} SOL) to {}",
+            reclaimable,
+            lamports_to_sol(reclaimable),
+            signer.pubkey()
+        );
 
         // Confirm the user wants to close.
         if !ask_confirm(
-            format!("{} You have {} ORE staked in this account.\nAre you sure you want to {}close this account? [Y/n]", 
+            format!("\n{} You have {} ORE staked in this account.\nAre you sure you want to {}close this account? [Y/n]",
                 "WARNING".yellow(),
-                amount_to_ui_amount(proof.balance, ore_api::consts::TOKEN_DECIMALS),
+                amount_u64_to_string(proof.balance),
                 if proof.balance.gt(&0) { "claim your stake and "} else { "" }
-            ).as_str()
+            ).as_str(),
+            self.assume_yes,
         ) {
-            return;
+            return Err(OreCliError::UserAbort);
         }
 
-        // Claim stake
+        // Claim stake, already confirmed above
         if proof.balance.gt(&0) {
-            self.claim(ClaimArgs {
-                amount: None,
-                to: None,
-            })
-            .await;
+            self.claim_to(to, proof.balance).await?;
         }
 
         // Submit close transaction
-        let ix = ore_api::instruction::close(signer.pubkey());
-        self.send_and_confirm(&[ix], ComputeBudget::Dynamic, false)
-            .await
-            .ok();
+        self.send_and_confirm(&close_ixs, ComputeBudget::Dynamic, false)
+            .await?;
+        Ok(())
+    }
+
+    /// Returns the rent held by a token account, if it exists and holds no tokens.
+    async fn empty_token_account_lamports(&self, address: &Pubkey) -> Result<Option<u64>> {
+        if get_token_balance(&self.rpc_client, address)
+            .await?
+            .map_or(true, |amount| amount.ne(&0))
+        {
+            return Ok(None);
+        }
+        Ok(Some(self.rpc_client.get_balance(address).await?))
     }
 }
@@ -0,0 +1,62 @@
+use solana_sdk::signature::Signer;
+
+use crate::{
+    args::{CompoundArgs, MineArgs},
+    cu_limits::CU_LIMIT_CLAIM,
+    error::Result,
+    send_and_confirm::ComputeBudget,
+    utils::{amount_u64_to_string, get_token_balance},
+    Miner,
+};
+
+impl Miner {
+    pub async fn compound(&self, args: CompoundArgs) -> Result<()> {
+        let threshold = args.threshold;
+        match self.compound_above(threshold).await? {
+            Some(amount) => println!("Staked {} ORE", amount_u64_to_string(amount)),
+            None => println!(
+                "Wallet balance does not exceed {} ORE, nothing to stake",
+                amount_u64_to_string(threshold)
+            ),
+        }
+        Ok(())
+    }
+
+    /// Compounds the wallet's balance after a mining round, if enabled.
+    pub async fn compound_after_round(&self, args: &MineArgs) -> Result<()> {
+        if !args.compound {
+            return Ok(());
+        }
+        let threshold = args.compound_threshold;
+        if let Some(amount) = self.compound_above(threshold).await? {
+            self.print(format!(
+                "Compounded {} ORE into stake",
+                amount_u64_to_string(amount)
+            ));
+        }
+        Ok(())
+    }
+
+    /// Stakes the wallet's whole ORE balance into the proof, if it exceeds the threshold.
+    /// Returns the amount staked.
+    pub async fn compound_above(&self, threshold: u64) -> Result<Option<u64>> {
+        // Get wallet balance
+        let signer = self.signer();
+        let sender = spl_associated_token_account::get_associated_token_address(
+            &signer.pubkey(),
+            &ore_api::consts::MINT_ADDRESS,
+        );
+        let Some(amount) = get_token_balance(&self.rpc_client, &sender).await? else {
+            return Ok(None);
+        };
+        if amount.le(&threshold) {
+            return Ok(None);
+        }
+
+        // Send tx
+        let ix = ore_api::instruction::stake(signer.pubkey(), sender, amount);
+        self.send_and_confirm(&[ix], ComputeBudget::Fixed(CU_LIMIT_CLAIM), false)
+            .await?;
+        Ok(Some(amount))
+    }
+}
@@ -1,19 +1,65 @@
 use colored::Colorize;
+use serde::Serialize;
 
 use crate::{
-    utils::{amount_u64_to_string, get_config},
+    error::Result,
+    output::{Report, TokenAmount},
+    utils::get_config,
     Miner,
 };
 
-impl Miner {
-    pub async fn config(&self) {
-        let config = get_config(&self.rpc_client).await;
-        println!("{}: {}", "Last reset".bold(), config.last_reset_at);
-        println!("{}: {}", "Top staker".bold(), config.top_staker);
-        println!(
-            "{}: {} ORE",
+#[derive(Serialize)]
+pub struct ConfigReport {
+    pub last_reset_at: i64,
+    pub top_staker: String,
+    pub max_stake: TokenAmount,
+    pub base_reward_rate: TokenAmount,
+}
+
+impl Report for ConfigReport {
+    fn text(&self) -> String {
+        format!(
+            "{}: {}\n{}: {}\n{}: {} ORE",
+            "Last reset".bold(),
+            self.last_reset_at,
+            "Top staker".bold(),
+            self.top_staker,
             "Top stake".bold(),
-            amount_u64_to_string(config.max_stake)
-        );
+            self.max_stake.ui_amount
+        )
+    }
+
+    fn csv_header(&self) -> Vec<&'static str> {
+        vec![
+            "last_reset_at",
+            "top_staker",
+            "max_stake",
+            "max_stake_ui",
+            "base_reward_rate",
+            "base_reward_rate_ui",
+        ]
+    }
+
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        vec![vec![
+            self.last_reset_at.to_string(),
+            self.top_staker.clone(),
+            self.max_stake.amount.to_string(),
+            self.max_stake.ui_amount.clone(),
+            self.base_reward_rate.amount.to_string(),
+            self.base_reward_rate.ui_amount.clone(),
+        ]]
+    }
+}
+
+impl Miner {
+    pub async fn config(&self) -> Result<()> {
+        let config = get_config(&self.rpc_client).await?;
+        self.print_report(&ConfigReport {
+            last_reset_at: config.last_reset_at,
+            top_staker: config.top_staker.to_string(),
+            max_stake: TokenAmount::new(config.max_stake),
+            base_reward_rate: TokenAmount::new(config.base_reward_rate),
+        })
     }
 }
@@ -0,0 +1,422 @@
+use std::{
+    collections::VecDeque,
+    io::{stdout, Stdout},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use indicatif::ProgressBar;
+use ore_api::{
+    consts::{BUS_ADDRESSES, EPOCH_DURATION},
+    state::Bus,
+};
+use ore_utils::AccountDeserialize;
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Layout},
+    style::{Color, Style, Stylize},
+    text::Line,
+    widgets::{Block, Borders, Paragraph, Row, Table},
+    Frame, Terminal,
+};
+use solana_program::{native_token::lamports_to_sol, pubkey::Pubkey};
+use solana_rpc_client::spinner;
+use solana_sdk::signature::{Signature, Signer};
+use tokio::task::JoinHandle as TaskHandle;
+
+use crate::{
+    error::{OreCliError, Result},
+    mining_pool::RoundProgress,
+    output::OutputFormat,
+    utils::{amount_u64_to_string, get_config},
+    Miner,
+};
+
+/// How often the dashboard is redrawn, in milliseconds.
+const DRAW_INTERVAL: u64 = 250;
+
+/// How often the bus and epoch panels are refetched, in seconds.
+const REFRESH_INTERVAL: u64 = 10;
+
+/// The number of recent submissions to show.
+const MAX_SUBMISSIONS: usize = 50;
+
+/// The number of recent messages to show.
+const MAX_MESSAGES: usize = 5;
+
+/// A solution sent by the mine loop, and how it turned out.
+struct Submission {
+    time: String,
+    difficulty: u32,
+    outcome: String,
+    signature: Option<Signature>,
+}
+
+#[derive(Default)]
+struct DashboardState {
+    authority: Option<Pubkey>,
+    deadline: Option<Instant>,
+    thread_hashrates: Vec<u64>,
+    hashrate: u64,
+    current_difficulty: u32,
+    best_difficulty: u32,
+    sol_balance: Option<u64>,
+    stake_balance: Option<u64>,
+    busses: Vec<u64>,
+    epoch_reset_at: Option<Instant>,
+    submissions: VecDeque<Submission>,
+    messages: VecDeque<String>,
+}
+
+/// A full-screen view of the mine loop, fed by the same paths that print progress otherwise.
+#[derive(Default)]
+pub struct Dashboard {
+    active: AtomicBool,
+    state: Mutex<DashboardState>,
+}
+
+/// Restores the terminal when mining stops.
+pub struct DashboardGuard {
+    dashboard: Arc<Dashboard>,
+    ui: Option<JoinHandle<()>>,
+    refresh: TaskHandle<()>,
+}
+
+impl Dashboard {
+    /// Whether the dashboard has taken over the terminal.
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    /// Shows the proof being hashed, and when its cutoff is.
+    pub fn start_round(&self, authority: Pubkey, stake_balance: u64, deadline: Instant) {
+        let mut state = self.state.lock().unwrap();
+        if state.authority.ne(&Some(authority)) || state.deadline.ne(&Some(deadline)) {
+            state.current_difficulty = 0;
+        }
+        state.authority = Some(authority);
+        state.stake_balance = Some(stake_balance);
+        state.deadline = Some(deadline);
+    }
+
+    pub fn record_progress(&self, progress: &RoundProgress) {
+        let mut state = self.state.lock().unwrap();
+        state.thread_hashrates = progress.thread_hashrates.clone();
+        state.hashrate = progress.hashrate;
+        state.current_difficulty = state.current_difficulty.max(progress.best_difficulty);
+        state.best_difficulty = state.best_difficulty.max(progress.best_difficulty);
+    }
+
+    pub fn record_submission(
+        &self,
+        difficulty: u32,
+        outcome: String,
+        signature: Option<Signature>,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        state.submissions.push_front(Submission {
+            time: chrono::Local::now().format("%H:%M:%S").to_string(),
+            difficulty,
+            outcome,
+            signature,
+        });
+        state.submissions.truncate(MAX_SUBMISSIONS);
+    }
+
+    pub fn set_sol_balance(&self, lamports: u64) {
+        self.state.lock().unwrap().sol_balance = Some(lamports);
+    }
+
+    pub fn set_stake_balance(&self, amount: u64) {
+        self.state.lock().unwrap().stake_balance = Some(amount);
+    }
+
+    /// Prints a line of progress, or shows it if the dashboard is active.
+    pub fn print(&self, msg: String) {
+        if self.is_active() {
+            self.log(msg);
+        } else {
+            println!("{}", msg);
+        }
+    }
+
+    /// Shows a message in place of printing it.
+    pub fn log(&self, msg: String) {
+        let mut state = self.state.lock().unwrap();
+        state.messages.push_front(msg.trim().to_string());
+        state.messages.truncate(MAX_MESSAGES);
+    }
+}
+
+impl Miner {
+    /// Takes over the terminal with the dashboard until the returned guard is dropped.
+    pub fn start_dashboard(&self) -> Result<DashboardGuard> {
+        // Read the keypairs first, since a missing keypair exits the process
+        let fee_payer = self.fee_payer().pubkey();
+
+        // Switch to a full-screen terminal
+        enable_raw_mode().map_err(terminal_error)?;
+        let terminal = execute!(stdout(), EnterAlternateScreen)
+            .and_then(|_| Terminal::new(CrosstermBackend::new(stdout())))
+            .map_err(|err| {
+                restore_terminal();
+                terminal_error(err)
+            })?;
+        let panic_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            restore_terminal();
+            panic_hook(info);
+        }));
+        colored::control::set_override(false);
+        self.dashboard.active.store(true, Ordering::Relaxed);
+
+        // Draw until mining stops or the user quits
+        let dashboard = self.dashboard.clone();
+        let ui = std::thread::spawn(move || draw_loop(terminal, dashboard));
+
+        // Refetch the state the mine loop does not see every round
+        let rpc_client = self.rpc_client.clone();
+        let clock = self.clock.clone();
+        let dashboard = self.dashboard.clone();
+        let refresh = tokio::spawn(async move {
+            loop {
+                if let Ok(accounts) = rpc_client.get_multiple_accounts(&BUS_ADDRESSES).await {
+                    let busses = accounts
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|account| Bus::try_from_bytes(&account.data).ok().copied())
+                        .map(|bus| bus.rewards)
+                        .collect();
+                    dashboard.state.lock().unwrap().busses = busses;
+                }
+                if let (Ok(config), Ok(now)) = (get_config(&rpc_client).await, clock.now().await) {
+                    let remaining = config
+                        .last_reset_at
+                        .saturating_add(EPOCH_DURATION)
+                        .saturating_sub(now)
+                        .max(0);
+                    dashboard.state.lock().unwrap().epoch_reset_at =
+                        Some(Instant::now() + Duration::from_secs(remaining as u64));
+                }
+                if let Ok(balance) = rpc_client.get_balance(&fee_payer).await {
+                    dashboard.set_sol_balance(balance);
+                }
+                tokio::time::sleep(Duration::from_secs(REFRESH_INTERVAL)).await;
+            }
+        });
+
+        Ok(DashboardGuard {
+            dashboard: self.dashboard.clone(),
+            ui: Some(ui),
+            refresh,
+        })
+    }
+
+    /// Prints a line of progress, or shows it on the dashboard if it is active. Outside of text
+    /// output, progress goes to stderr so stdout holds only the report.
+    pub fn print(&self, msg: String) {
+        if self.output.eq(&OutputFormat::Text) || self.dashboard.is_active() {
+            self.dashboard.print(msg);
+        } else {
+            eprintln!("{}", msg);
+        }
+    }
+
+    /// A spinner, hidden while the dashboard is active.
+    pub fn progress_bar(&self) -> ProgressBar {
+        if self.dashboard.is_active() {
+            ProgressBar::hidden()
+        } else {
+            spinner::new_progress_bar()
+        }
+    }
+}
+
+impl Drop for DashboardGuard {
+    fn drop(&mut self) {
+        self.dashboard.active.store(false, Ordering::Relaxed);
+        self.refresh.abort();
+        if let Some(ui) = self.ui.take() {
+            ui.join().ok();
+        }
+    }
+}
+
+fn draw_loop(mut terminal: Terminal<CrosstermBackend<Stdout>>, dashboard: Arc<Dashboard>) {
+    while dashboard.is_active() {
+        terminal.draw(|frame| draw(frame, &dashboard)).ok();
+
+        // Raw mode swallows ctrl-c, so quit on it here
+        if event::poll(Duration::from_millis(DRAW_INTERVAL)).unwrap_or(false) {
+            if let Ok(Event::Key(key)) = event::read() {
+                let quit = key.kind.eq(&KeyEventKind::Press)
+                    && (matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+                        || (key.code.eq(&KeyCode::Char('c'))
+                            && key.modifiers.contains(KeyModifiers::CONTROL)));
+                if quit {
+                    restore_terminal();
+                    let err = OreCliError::UserAbort;
+                    tracing::debug!(exit_code = err.exit_code(), "Quit from the dashboard");
+                    eprintln!("{}", err);
+                    std::process::exit(err.exit_code());
+                }
+            }
+        }
+    }
+    restore_terminal();
+}
+
+fn draw(frame: &mut Frame, dashboard: &Dashboard) {
+    let state = dashboard.state.lock().unwrap();
+    let [top, middle, messages, footer] = Layout::vertical([
+        Constraint::Length(BUS_ADDRESSES.len() as u16 + 3),
+        Constraint::Min(6),
+        Constraint::Length(MAX_MESSAGES as u16 + 2),
+        Constraint::Length(1),
+    ])
+    .areas(frame.size());
+    let [mining, balances, epoch] = Layout::horizontal([
+        Constraint::Percentage(40),
+        Constraint::Percentage(25),
+        Constraint::Percentage(35),
+    ])
+    .areas(top);
+    let [threads, submissions] =
+        Layout::horizontal([Constraint::Length(28), Constraint::Min(40)]).areas(middle);
+
+    // Round progress
+    let cutoff = match state.deadline {
+        Some(deadline) => {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                "Submitting".to_string()
+            } else {
+                format!("{} sec", remaining.as_secs())
+            }
+        }
+        None => "-".to_string(),
+    };
+    let lines = vec![
+        Line::from(format!(
+            "Authority: {}",
+            state.authority.map_or("-".to_string(), |a| a.to_string())
+        )),
+        Line::from(format!("Cutoff: {}", cutoff)),
+        Line::from(format!("Hashrate: {} H/sec", state.hashrate)),
+        Line::from(format!("Current difficulty: {}", state.current_difficulty)),
+        Line::from(format!("Best difficulty: {}", state.best_difficulty)),
+    ];
+    frame.render_widget(Paragraph::new(lines).block(panel("Mining")), mining);
+
+    // Balances
+    let lines = vec![
+        Line::from(format!(
+            "SOL: {}",
+            state
+                .sol_balance
+                .map_or("-".to_string(), |b| lamports_to_sol(b).to_string())
+        )),
+        Line::from(format!(
+            "Stake: {} ORE",
+            state
+                .stake_balance
+                .map_or("-".to_string(), amount_u64_to_string)
+        )),
+    ];
+    frame.render_widget(Paragraph::new(lines).block(panel("Balances")), balances);
+
+    // Bus rewards and the next reset
+    let mut lines = vec![Line::from(format!(
+        "Reset in: {}",
+        state.epoch_reset_at.map_or("-".to_string(), |at| format!(
+            "{} sec",
+            at.saturating_duration_since(Instant::now()).as_secs()
+        ))
+    ))];
+    for (id, rewards) in state.busses.iter().enumerate() {
+        lines.push(Line::from(format!(
+            "Bus {}: {} ORE",
+            id,
+            amount_u64_to_string(*rewards)
+        )));
+    }
+    frame.render_widget(Paragraph::new(lines).block(panel("Epoch")), epoch);
+
+    // Per-thread hashrate
+    let rows = state
+        .thread_hashrates
+        .iter()
+        .enumerate()
+        .map(|(thread, hashrate)| {
+            Row::new(vec![thread.to_string(), format!("{} H/sec", hashrate)])
+        });
+    frame.render_widget(
+        Table::new(rows, [Constraint::Length(6), Constraint::Min(10)])
+            .header(Row::new(vec!["Thread", "Hashrate"]).bold())
+            .block(panel("Threads")),
+        threads,
+    );
+
+    // Recent submissions
+    let rows = state.submissions.iter().map(|submission| {
+        let style = if submission.signature.is_some() {
+            Style::default().fg(Color::Green)
+        } else {
+            Style::default().fg(Color::Yellow)
+        };
+        Row::new(vec![
+            submission.time.clone(),
+            submission.difficulty.to_string(),
+            submission.outcome.clone(),
+            submission
+                .signature
+                .map_or("-".to_string(), |sig| sig.to_string()),
+        ])
+        .style(style)
+    });
+    frame.render_widget(
+        Table::new(
+            rows,
+            [
+                Constraint::Length(8),
+                Constraint::Length(10),
+                Constraint::Length(24),
+                Constraint::Min(20),
+            ],
+        )
+        .header(Row::new(vec!["Time", "Difficulty", "Outcome", "Signature"]).bold())
+        .block(panel("Submissions")),
+        submissions,
+    );
+
+    // Messages and help
+    let lines: Vec<Line> = state
+        .messages
+        .iter()
+        .map(|msg| Line::from(msg.as_str()))
+        .collect();
+    frame.render_widget(Paragraph::new(lines).block(panel("Log")), messages);
+    frame.render_widget(Paragraph::new("Press q to quit").dim(), footer);
+}
+
+fn panel(title: &str) -> Block {
+    Block::default().borders(Borders::ALL).title(title)
+}
+
+fn restore_terminal() {
+    disable_raw_mode().ok();
+    execute!(stdout(), LeaveAlternateScreen).ok();
+}
+
+fn terminal_error(err: std::io::Error) -> OreCliError {
+    OreCliError::InvalidArgs(format!("Failed to start the dashboard: {}", err))
+}
@@ -0,0 +1,110 @@
+use std::fmt;
+
+use solana_client::client_error::ClientError;
+use solana_program::native_token::lamports_to_sol;
+use solana_sdk::transaction::TransactionError;
+
+use crate::program_errors::FailedInstruction;
+
+pub type Result<T> = std::result::Result<T, OreCliError>;
+
+#[derive(Debug)]
+pub enum OreCliError {
+    /// An rpc request failed or timed out.
+    Rpc(ClientError),
+
+    /// An account could not be parsed, or its data could not be formatted.
+    Parse(String),
+
+    /// An argument is malformed or cannot be used, like an address that is already in use.
+    InvalidArgs(String),
+
+    /// An account the command needs does not exist.
+    AccountNotFound(String),
+
+    /// The fee payer cannot cover the transaction fees.
+    InsufficientBalance { balance: u64, required: u64 },
+
+    /// A transaction failed in simulation or on-chain.
+    Program {
+        err: TransactionError,
+        failed: Option<FailedInstruction>,
+        logs: Vec<String>,
+    },
+
+    /// A transaction was sent but not confirmed before running out of retries.
+    NotLanded { attempts: usize },
+
+    /// The user declined to continue.
+    UserAbort,
+}
+
+/// The process exit codes, one per outcome, so scripts can react to failures.
+pub const EXIT_PARSE: i32 = 1;
+pub const EXIT_INVALID_ARGS: i32 = 2;
+pub const EXIT_USER_ABORT: i32 = 3;
+pub const EXIT_INSUFFICIENT_BALANCE: i32 = 4;
+pub const EXIT_RPC: i32 = 5;
+pub const EXIT_TRANSACTION_FAILED: i32 = 6;
+
+/// The exit codes, as listed in the help text.
+pub const EXIT_CODES_HELP: &str = "Exit codes:
+  0  Success
+  1  Missing keypair, or an account that could not be parsed
+  2  Invalid arguments, including malformed addresses and amounts
+  3  Aborted, by the user or for lack of a terminal to confirm with
+  4  Insufficient SOL to pay transaction fees
+  5  RPC request failed or timed out
+  6  Transaction failed or did not land";
+
+impl OreCliError {
+    /// The process exit code to report for this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            OreCliError::Parse(_) | OreCliError::AccountNotFound(_) => EXIT_PARSE,
+            OreCliError::InvalidArgs(_) => EXIT_INVALID_ARGS,
+            OreCliError::UserAbort => EXIT_USER_ABORT,
+            OreCliError::InsufficientBalance { .. } => EXIT_INSUFFICIENT_BALANCE,
+            OreCliError::Rpc(_) => EXIT_RPC,
+            OreCliError::Program { .. } | OreCliError::NotLanded { .. } => EXIT_TRANSACTION_FAILED,
+        }
+    }
+}
+
+impl fmt::Display for OreCliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OreCliError::Rpc(err) => write!(f, "RPC error: {}", err.kind()),
+            OreCliError::Parse(msg) | OreCliError::InvalidArgs(msg) => write!(f, "{}", msg),
+            OreCliError::AccountNotFound(account) => write!(f, "{} does not exist", account),
+            OreCliError::InsufficientBalance { balance, required } => write!(
+                f,
+                "Insufficient balance: {} SOL\nPlease top up with at least {} SOL",
+                lamports_to_sol(*balance),
+                lamports_to_sol(*required)
+            ),
+            OreCliError::Program { err, failed, logs } => {
+                match failed {
+                    Some(failed) => write!(f, "Transaction failed: {}", failed)?,
+                    None => write!(f, "Transaction failed: {}", err)?,
+                }
+                for log in logs {
+                    write!(f, "\n  {}", log)?;
+                }
+                Ok(())
+            }
+            OreCliError::NotLanded { attempts } => {
+                write!(f, "Transaction did not land after {} attempts", attempts)
+            }
+            OreCliError::UserAbort => write!(f, "Aborted"),
+        }
+    }
+}
+
+impl std::error::Error for OreCliError {}
+
+impl From<ClientError> for OreCliError {
+    fn from(err: ClientError) -> Self {
+        OreCliError::Rpc(err)
+    }
+}
@@ -0,0 +1,352 @@
+use std::{
+    ops::Range,
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use colored::*;
+use drillx::Solution;
+use ore_api::state::Proof;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signer::Signer;
+use tokio::sync::mpsc::unbounded_channel;
+use tracing::Instrument;
+
+use crate::{
+    adaptive_buffer::AdaptiveBuffer,
+    args::MineArgs,
+    error::{OreCliError, Result},
+    mine::round_span,
+    mining_pool::MiningPool,
+    nonce_allocator::NonceAllocator,
+    utils::{amount_u64_to_string, get_proof_with_authority},
+    Miner,
+};
+
+/// The longest the pool hashes one keypair's challenge before rotating, in seconds.
+const ROTATION_SLICE: u64 = 5;
+
+/// The delay before retrying a keypair whose round failed to start, in seconds.
+const ROUND_RETRY_DELAY: u64 = 5;
+
+/// The delay before checking again when no keypair has work to hash, in milliseconds.
+const IDLE_DELAY: u64 = 500;
+
+/// A keypair's progress on its current challenge.
+struct Round {
+    proof: Proof,
+    deadline: Instant,
+    nonces: Arc<NonceAllocator>,
+    best: Option<(Solution, u32)>,
+    hashed_for: Duration,
+    hashes: u64,
+}
+
+enum MemberState {
+    /// Waiting to fetch a new challenge at the given time.
+    Idle(Instant),
+    Hashing(Box<Round>),
+    Submitting,
+    /// Stopped for lack of funds.
+    Stopped,
+}
+
+struct FleetMember {
+    miner: Arc<Miner>,
+    pubkey: Pubkey,
+    state: MemberState,
+}
+
+impl Miner {
+    /// Mines with many keypairs on one worker pool, rotating the pool between their challenges.
+    pub async fn mine_fleet(
+        &self,
+        keypairs: &str,
+        args: Arc<MineArgs>,
+        pool: &mut MiningPool,
+        buffer: Arc<AdaptiveBuffer>,
+        nonces: Range<u64>,
+    ) -> Result<()> {
+        // Register each keypair, if needed
+        let mut members = vec![];
+        for keypair_filepath in read_keypair_filepaths(keypairs)? {
+            let miner = Arc::new(self.with_keypair(keypair_filepath));
+            miner.register().await?;
+            members.push(FleetMember {
+                pubkey: miner.signer().pubkey(),
+                miner,
+                state: MemberState::Idle(Instant::now()),
+            });
+        }
+        self.print(format!("Mining with {} keypairs", members.len()));
+
+        // Start mining loop
+        let (results_tx, mut results_rx) = unbounded_channel::<(usize, Result<()>)>();
+        let mut last_err = None;
+        loop {
+            // Collect finished submissions
+            while let Ok((i, res)) = results_rx.try_recv() {
+                let member = &mut members[i];
+                member.state = MemberState::Idle(Instant::now());
+                match res {
+                    Ok(()) => {}
+                    Err(err @ OreCliError::InsufficientBalance { .. }) => {
+                        tracing::error!(authority = %member.pubkey, %err, "Keypair stopped");
+                        self.print(format!(
+                            "{} {}: {}",
+                            "ERROR".bold().red(),
+                            member.pubkey,
+                            err
+                        ));
+                        member.state = MemberState::Stopped;
+                        last_err = Some(err);
+                    }
+                    Err(err) => {
+                        tracing::error!(authority = %member.pubkey, %err, "Mining round failed");
+                        self.print(format!(
+                            "{} {}: {}",
+                            "ERROR".bold().red(),
+                            member.pubkey,
+                            err
+                        ));
+                    }
+                }
+            }
+            // Stop once every keypair has run out of funds
+            if let Some(err) = last_err.take() {
+                if members
+                    .iter()
+                    .all(|m| matches!(m.state, MemberState::Stopped))
+                {
+                    return Err(err);
+                }
+            }
+
+            // Start new rounds for idle keypairs
+            for member in members.iter_mut() {
+                let MemberState::Idle(ready_at) = member.state else {
+                    continue;
+                };
+                if Instant::now().lt(&ready_at) {
+                    continue;
+                }
+                match member.start_round(&args, &buffer, nonces.clone()).await {
+                    Ok(round) => member.state = MemberState::Hashing(Box::new(round)),
+                    Err(err) => {
+                        self.print(format!(
+                            "{} {}: {}",
+                            "ERROR".bold().red(),
+                            member.pubkey,
+                            err
+                        ));
+                        member.state = MemberState::Idle(
+                            Instant::now() + Duration::from_secs(ROUND_RETRY_DELAY),
+                        );
+                    }
+                }
+            }
+
+            // Submit solutions that are due
+            for (i, member) in members.iter_mut().enumerate() {
+                let MemberState::Hashing(round) = &member.state else {
+                    continue;
+                };
+                let Some((solution, difficulty)) = round.best else {
+                    continue;
+                };
+                if !round.is_due(&args) {
+                    continue;
+                }
+                self.print(format!(
+                    "{}: Submitting hash (difficulty: {})",
+                    member.pubkey, difficulty
+                ));
+                let proof = round.proof;
+                let span = round_span(member.pubkey, proof);
+                span.in_scope(|| {
+                    tracing::info!(
+                        best_difficulty = difficulty,
+                        hashes = round.hashes,
+                        "Hashing finished"
+                    )
+                });
+                self.metrics.record_best_difficulty(difficulty);
+                let miner = member.miner.clone();
+                let args = args.clone();
+                let buffer = buffer.clone();
+                let results_tx = results_tx.clone();
+                tokio::spawn(
+                    async move {
+                        let signer = miner.signer();
+                        let res = async {
+                            miner.wait_for_spam_window(proof).await?;
+                            miner
+                                .submit(&signer, &args, proof, solution, difficulty, &buffer)
+                                .await?;
+                            miner.auto_claim_after_round(&args).await?;
+                            miner.compound_after_round(&args).await
+                        }
+                        .await;
+                        results_tx.send((i, res)).ok();
+                    }
+                    .instrument(span),
+                );
+                member.state = MemberState::Submitting;
+            }
+
+            // Hash the most urgent challenge for one slice
+            let Some(member) = next_member(&mut members) else {
+                tokio::time::sleep(Duration::from_millis(IDLE_DELAY)).await;
+                continue;
+            };
+            let pubkey = member.pubkey;
+            let MemberState::Hashing(round) = &mut member.state else {
+                continue;
+            };
+            round.hash_slice(pool, &args, pubkey, self).await;
+        }
+    }
+}
+
+impl FleetMember {
+    async fn start_round(
+        &self,
+        args: &MineArgs,
+        buffer: &AdaptiveBuffer,
+        nonces: Range<u64>,
+    ) -> Result<Round> {
+        let proof = get_proof_with_authority(&self.miner.rpc_client, self.pubkey).await?;
+        self.miner
+            .metrics
+            .set_stake_balance(self.pubkey, proof.balance);
+        let buffer_time = args.buffer_time.unwrap_or_else(|| buffer.buffer_time());
+        let cutoff_time = self.miner.get_cutoff(proof, buffer_time).await?;
+        self.miner.print(format!(
+            "{}: Stake balance: {} ORE, {} sec until cutoff",
+            self.pubkey,
+            amount_u64_to_string(proof.balance),
+            cutoff_time
+        ));
+        Ok(Round {
+            proof,
+            deadline: Instant::now() + Duration::from_secs(cutoff_time),
+            nonces: Arc::new(NonceAllocator::new(nonces)),
+            best: None,
+            hashed_for: Duration::ZERO,
+            hashes: 0,
+        })
+    }
+}
+
+impl Round {
+    fn best_difficulty(&self) -> u32 {
+        self.best.map_or(0, |(_, difficulty)| difficulty)
+    }
+
+    /// Whether the best solution has reached the target difficulty, or the deadline has passed
+    /// and it meets the min difficulty.
+    fn is_due(&self, args: &MineArgs) -> bool {
+        let difficulty = self.best_difficulty();
+        args.target_difficulty.is_some_and(|t| difficulty.ge(&t))
+            || (Instant::now().ge(&self.deadline) && difficulty.ge(&args.min_difficulty))
+    }
+
+    async fn hash_slice(
+        &mut self,
+        pool: &mut MiningPool,
+        args: &MineArgs,
+        pubkey: Pubkey,
+        miner: &Miner,
+    ) {
+        // Past the deadline, hash until the min difficulty is met
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        let (cutoff_time, min_difficulty) = if remaining.is_zero() {
+            (0, args.min_difficulty)
+        } else {
+            (remaining.as_secs().min(ROTATION_SLICE), 0)
+        };
+
+        // Continue on this challenge's nonces where the last slice left off
+        let timer = Instant::now();
+        let progress_bar = miner.progress_bar();
+        let best_difficulty = self.best_difficulty();
+        let deadline = self.deadline;
+        miner
+            .dashboard
+            .start_round(pubkey, self.proof.balance, deadline);
+        let (solution, _hash, difficulty) = pool
+            .find_hash(
+                self.proof.challenge,
+                cutoff_time,
+                min_difficulty,
+                args.target_difficulty,
+                self.nonces.clone(),
+                |_, mut progress| {
+                    progress.best_difficulty = progress.best_difficulty.max(best_difficulty);
+                    miner.dashboard.record_progress(&progress);
+                    progress_bar.set_message(format!(
+                        "Mining {}... ({} sec remaining, best difficulty: {}, {} H/sec)",
+                        pubkey,
+                        deadline.saturating_duration_since(Instant::now()).as_secs(),
+                        progress.best_difficulty,
+                        progress.hashrate
+                    ));
+                },
+            )
+            .await;
+        progress_bar.finish_and_clear();
+
+        // Keep the best solution across slices
+        self.hashed_for += timer.elapsed();
+        self.hashes += pool.hashes();
+        miner
+            .metrics
+            .record_hashes(&pool.worker_hashes(), timer.elapsed());
+        if difficulty.gt(&self.best_difficulty()) || self.best.is_none() {
+            self.best = Some((solution, difficulty));
+        }
+    }
+}
+
+/// Picks the keypair to hash next. Keypairs past their deadline that still need the min
+/// difficulty go first, then the keypair that has had the least time on the pool.
+fn next_member(members: &mut [FleetMember]) -> Option<&mut FleetMember> {
+    let now = Instant::now();
+    members
+        .iter_mut()
+        .filter(|m| matches!(m.state, MemberState::Hashing(_)))
+        .min_by_key(|m| match &m.state {
+            MemberState::Hashing(round) => (now.lt(&round.deadline), round.hashed_for),
+            _ => (true, Duration::MAX),
+        })
+}
+
+/// Reads keypair filepaths from a directory of keypair files or a comma separated list.
+pub fn read_keypair_filepaths(keypairs: &str) -> Result<Vec<String>> {
+    let path = Path::new(keypairs);
+    let mut filepaths = if path.is_dir() {
+        std::fs::read_dir(path)
+            .map_err(|err| {
+                OreCliError::InvalidArgs(format!("Failed to read {}: {}", keypairs, err))
+            })?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .map(|path| path.to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+    } else {
+        keypairs
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect()
+    };
+    filepaths.sort();
+    if filepaths.is_empty() {
+        return Err(OreCliError::InvalidArgs(format!(
+            "No keypairs found in {}",
+            keypairs
+        )));
+    }
+    Ok(filepaths)
+}
@@ -1,17 +1,17 @@
 use ore_api::consts::TREASURY_ADDRESS;
 use solana_sdk::{signature::Signer, transaction::Transaction};
 
-use crate::Miner;
+use crate::{error::Result, Miner};
 
 impl Miner {
-    pub async fn initialize(&self) {
+    pub async fn initialize(&self) -> Result<()> {
         // Return early if program is already initialized
         if self.rpc_client.get_account(&TREASURY_ADDRESS).await.is_ok() {
-            return;
+            return Ok(());
         }
 
         // Submit initialize tx
-        let blockhash = self.rpc_client.get_latest_blockhash().await.unwrap();
+        let blockhash = self.rpc_client.get_latest_blockhash().await?;
         let ix = ore_api::instruction::initialize(self.signer().pubkey());
         let tx = Transaction::new_signed_with_payer(
             &[ix],
@@ -21,5 +21,6 @@ impl Miner {
         );
         let res = self.rpc_client.send_and_confirm_transaction(&tx).await;
         println!("{:?}", res);
+        Ok(())
     }
 }
@@ -0,0 +1,117 @@
+use std::{io::IsTerminal, path::Path, str::FromStr};
+
+use tracing::level_filters::LevelFilter;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
+
+use crate::error::{OreCliError, Result};
+
+#[derive(Clone, Copy, Debug)]
+pub enum LogRotation {
+    Hourly,
+    Daily,
+    Never,
+}
+
+impl FromStr for LogRotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "hourly" => Ok(LogRotation::Hourly),
+            "daily" => Ok(LogRotation::Daily),
+            "never" => Ok(LogRotation::Never),
+            _ => Err(format!("expected hourly, daily, or never, got {}", s)),
+        }
+    }
+}
+
+/// The log levels in order of verbosity, indexed by -v and -q.
+const LEVELS: [LevelFilter; 6] = [
+    LevelFilter::OFF,
+    LevelFilter::ERROR,
+    LevelFilter::WARN,
+    LevelFilter::INFO,
+    LevelFilter::DEBUG,
+    LevelFilter::TRACE,
+];
+
+/// The default level for logs to a file, and to stderr when it is not a terminal.
+const DEFAULT_LEVEL: usize = 3;
+
+/// The default level for logs to a terminal, where the spinners already show progress.
+const DEFAULT_TERMINAL_LEVEL: usize = 2;
+
+/// Logs to stderr, unless the dashboard is shown, and to a rotating json log file if given. Each
+/// -v raises and each -q lowers the level from its default.
+pub fn init(
+    verbose: u8,
+    quiet: u8,
+    log_file: Option<String>,
+    log_rotation: LogRotation,
+    tui: bool,
+) -> Result<()> {
+    let level = |default: usize| {
+        LEVELS[(default + verbose as usize)
+            .saturating_sub(quiet as usize)
+            .min(LEVELS.len() - 1)]
+    };
+
+    // Keep terminals quiet for the spinner UI, but log rounds under service managers
+    let stderr_level = if std::io::stderr().is_terminal() {
+        level(DEFAULT_TERMINAL_LEVEL)
+    } else {
+        level(DEFAULT_LEVEL)
+    };
+    let stderr_layer = (!tui).then(|| {
+        tracing_subscriber::fmt::layer()
+            .with_writer(std::io::stderr)
+            .with_ansi(std::io::stderr().is_terminal())
+            .with_filter(stderr_level)
+    });
+
+    // Write structured events to the log file
+    let file_layer = match log_file {
+        Some(log_file) => {
+            let path = Path::new(&log_file);
+            let directory = path
+                .parent()
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .unwrap_or(Path::new("."));
+            let prefix = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .ok_or_else(|| {
+                    OreCliError::InvalidArgs(format!("Invalid log file: {}", log_file))
+                })?;
+            let rotation = match log_rotation {
+                LogRotation::Hourly => Rotation::HOURLY,
+                LogRotation::Daily => Rotation::DAILY,
+                LogRotation::Never => Rotation::NEVER,
+            };
+            let appender = RollingFileAppender::builder()
+                .rotation(rotation)
+                .filename_prefix(prefix)
+                .build(directory)
+                .map_err(|err| {
+                    OreCliError::InvalidArgs(format!(
+                        "Failed to open log file {}: {}",
+                        log_file, err
+                    ))
+                })?;
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .json()
+                    .with_writer(appender)
+                    .with_filter(level(DEFAULT_LEVEL)),
+            )
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(stderr_layer)
+        .with(file_layer)
+        .try_init()
+        .map_err(|err| OreCliError::InvalidArgs(format!("Failed to start logging: {}", err)))
+}
@@ -1,15 +1,30 @@
+mod adaptive_buffer;
 mod args;
 mod balance;
 mod benchmark;
+mod bus_strategy;
 mod busses;
+mod chain_clock;
 mod claim;
+mod claim_split;
 mod close;
+mod compound;
 mod config;
 mod cu_limits;
+mod dashboard;
+mod error;
+mod fleet;
 #[cfg(feature = "admin")]
 mod initialize;
+mod logging;
+mod metrics;
 mod mine;
+mod mining_pool;
+mod nonce_allocator;
 mod open;
+mod output;
+mod priority_fee;
+mod program_errors;
 mod rewards;
 mod send_and_confirm;
 mod stake;
@@ -19,17 +34,36 @@ mod utils;
 use std::sync::Arc;
 
 use args::*;
-use clap::{command, Parser, Subcommand};
+use chain_clock::ChainClock;
+use clap::{command, ArgAction, Parser, Subcommand};
+use colored::*;
+use dashboard::Dashboard;
+use error::{EXIT_CODES_HELP, EXIT_INVALID_ARGS, EXIT_PARSE};
+use logging::LogRotation;
+use metrics::Metrics;
+use output::OutputFormat;
+use priority_fee::PriorityFee;
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{
     commitment_config::CommitmentConfig,
-    signature::{read_keypair_file, Keypair},
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signer},
 };
 
+#[derive(Clone)]
 struct Miner {
     pub keypair_filepath: Option<String>,
-    pub priority_fee: u64,
+    pub fee_payer_filepath: Option<String>,
+    pub authority: Option<Pubkey>,
+    pub output: OutputFormat,
+    pub assume_yes: bool,
+    pub priority_fee: PriorityFee,
+    pub priority_fee_percentile: u8,
+    pub max_priority_fee: u64,
     pub rpc_client: Arc<RpcClient>,
+    pub clock: Arc<ChainClock>,
+    pub metrics: Arc<Metrics>,
+    pub dashboard: Arc<Dashboard>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -49,12 +83,18 @@ enum Commands {
     #[command(about = "Close your account to recover rent")]
     Close(CloseArgs),
 
+    #[command(about = "Stake the Ore in your wallet to grow your rewards multiplier")]
+    Compound(CompoundArgs),
+
     #[command(about = "Fetch the program config")]
     Config(ConfigArgs),
 
     #[command(about = "Start mining")]
     Mine(MineArgs),
 
+    #[command(about = "Open a proof account to mine with")]
+    Open(OpenArgs),
+
     #[command(about = "Fetch the current reward rate for each difficulty level")]
     Rewards(RewardsArgs),
 
@@ -70,7 +110,7 @@ enum Commands {
 }
 
 #[derive(Parser, Debug)]
-#[command(about, version)]
+#[command(about, version, after_help = EXIT_CODES_HELP)]
 struct Args {
     #[arg(
         long,
@@ -97,14 +137,102 @@ struct Args {
     )]
     keypair: Option<String>,
 
+    #[arg(
+        long,
+        value_name = "KEYPAIR_FILEPATH",
+        help = "Filepath to keypair to pay transaction fees and rent with, defaults to the keypair",
+        global = true
+    )]
+    fee_payer: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        help = "Authority of the proof to mine for and fetch balances of, when the keypair is only the proof's miner",
+        global = true
+    )]
+    authority: Option<Pubkey>,
+
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        help = "Format to print query results in: text, json, or csv",
+        default_value = "text",
+        global = true
+    )]
+    output: OutputFormat,
+
+    #[arg(
+        long,
+        short = 'y',
+        visible_alias = "no-confirm",
+        help = "Skip confirmation prompts and continue",
+        global = true
+    )]
+    yes: bool,
+
+    #[arg(
+        long,
+        short = 'v',
+        action = ArgAction::Count,
+        help = "Log more detail, repeat for even more",
+        global = true
+    )]
+    verbose: u8,
+
+    #[arg(
+        long,
+        short = 'q',
+        action = ArgAction::Count,
+        help = "Log less detail, repeat for even less",
+        global = true
+    )]
+    quiet: u8,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Filepath to write json logs to, rotated by the log rotation",
+        global = true
+    )]
+    log_file: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "ROTATION",
+        help = "How often to start a new log file: hourly, daily, or never",
+        default_value = "daily",
+        global = true
+    )]
+    log_rotation: LogRotation,
+
     #[arg(
         long,
         value_name = "MICROLAMPORTS",
-        help = "Number of microlamports to pay as priority fee per transaction",
+        help = "Number of microlamports to pay as priority fee per transaction, or \"auto\" to estimate from recent fees",
         default_value = "0",
         global = true
     )]
-    priority_fee: u64,
+    priority_fee: PriorityFee,
+
+    #[arg(
+        long,
+        value_name = "PERCENTILE",
+        help = "Percentile of recent priority fees to pay when using an auto priority fee",
+        default_value = "75",
+        value_parser = clap::value_parser!(u8).range(0..=100),
+        global = true
+    )]
+    priority_fee_percentile: u8,
+
+    #[arg(
+        long,
+        value_name = "MICROLAMPORTS",
+        help = "Maximum number of microlamports to pay when using an auto priority fee",
+        default_value = "500000",
+        global = true
+    )]
+    max_priority_fee: u64,
 
     #[command(subcommand)]
     command: Commands,
@@ -114,11 +242,24 @@ struct Args {
 async fn main() {
     let args = Args::parse();
 
+    // Start logging, leaving the terminal to the dashboard if it is shown
+    let tui = matches!(&args.command, Commands::Mine(args) if args.tui);
+    if let Err(err) = logging::init(
+        args.verbose,
+        args.quiet,
+        args.log_file,
+        args.log_rotation,
+        tui,
+    ) {
+        eprintln!("{} {}", "ERROR".bold().red(), err);
+        std::process::exit(err.exit_code());
+    }
+
     // Load the config file from custom path, the default path, or use default config values
     let cli_config = if let Some(config_file) = &args.config_file {
         solana_cli_config::Config::load(config_file).unwrap_or_else(|_| {
             eprintln!("error: Could not find config file `{}`", config_file);
-            std::process::exit(1);
+            std::process::exit(EXIT_INVALID_ARGS);
         })
     } else if let Some(config_file) = &*solana_cli_config::CONFIG_FILE {
         solana_cli_config::Config::load(config_file).unwrap_or_default()
@@ -134,66 +275,117 @@ async fn main() {
     let miner = Arc::new(Miner::new(
         Arc::new(rpc_client),
         args.priority_fee,
+        args.priority_fee_percentile,
+        args.max_priority_fee,
         Some(default_keypair),
+        args.fee_payer,
+        args.authority,
+        args.output,
+        args.yes,
     ));
 
     // Execute user command.
-    match args.command {
-        Commands::Balance(args) => {
-            miner.balance(args).await;
-        }
-        Commands::Benchmark(args) => {
-            miner.benchmark(args).await;
-        }
-        Commands::Busses(_) => {
-            miner.busses().await;
-        }
-        Commands::Claim(args) => {
-            miner.claim(args).await;
-        }
-        Commands::Close(_) => {
-            miner.close().await;
-        }
-        Commands::Config(_) => {
-            miner.config().await;
-        }
-        Commands::Mine(args) => {
-            miner.mine(args).await;
-        }
-        Commands::Rewards(_) => {
-            miner.rewards().await;
-        }
-        Commands::Stake(args) => {
-            miner.stake(args).await;
-        }
-        Commands::Upgrade(args) => {
-            miner.upgrade(args).await;
-        }
+    let res = match args.command {
+        Commands::Balance(args) => miner.balance(args).await,
+        Commands::Benchmark(args) => miner.benchmark(args).await,
+        Commands::Busses(_) => miner.busses().await,
+        Commands::Claim(args) => miner.claim(args).await,
+        Commands::Close(args) => miner.close(args).await,
+        Commands::Compound(args) => miner.compound(args).await,
+        Commands::Config(_) => miner.config().await,
+        Commands::Mine(args) => miner.mine(args).await,
+        Commands::Open(args) => miner.open(args).await,
+        Commands::Rewards(_) => miner.rewards().await,
+        Commands::Stake(args) => miner.stake(args).await,
+        Commands::Upgrade(args) => miner.upgrade(args).await,
         #[cfg(feature = "admin")]
-        Commands::Initialize(_) => {
-            miner.initialize().await;
-        }
+        Commands::Initialize(_) => miner.initialize().await,
+    };
+
+    // Report errors and exit with the matching code, logging below the terminal's level so the
+    // error is printed once
+    if let Err(err) = res {
+        tracing::debug!(%err, exit_code = err.exit_code(), "Command failed");
+        eprintln!("{} {}", "ERROR".bold().red(), err);
+        std::process::exit(err.exit_code());
     }
 }
 
 impl Miner {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         rpc_client: Arc<RpcClient>,
-        priority_fee: u64,
+        priority_fee: PriorityFee,
+        priority_fee_percentile: u8,
+        max_priority_fee: u64,
         keypair_filepath: Option<String>,
+        fee_payer_filepath: Option<String>,
+        authority: Option<Pubkey>,
+        output: OutputFormat,
+        assume_yes: bool,
     ) -> Self {
+        let dashboard = Arc::new(Dashboard::default());
         Self {
+            clock: Arc::new(ChainClock::new(rpc_client.clone(), dashboard.clone())),
+            metrics: Arc::new(Metrics::default()),
+            dashboard,
             rpc_client,
             keypair_filepath,
+            fee_payer_filepath,
+            authority,
+            output,
+            assume_yes,
             priority_fee,
+            priority_fee_percentile,
+            max_priority_fee,
+        }
+    }
+
+    /// Returns a miner with the same settings that signs with a different keypair, for its own
+    /// proof.
+    pub fn with_keypair(&self, keypair_filepath: String) -> Self {
+        Self {
+            keypair_filepath: Some(keypair_filepath),
+            authority: None,
+            ..self.clone()
+        }
+    }
+
+    /// Returns a miner with the same settings that pays fees and rent with a different keypair.
+    pub fn with_fee_payer(&self, fee_payer_filepath: String) -> Self {
+        Self {
+            fee_payer_filepath: Some(fee_payer_filepath),
+            ..self.clone()
         }
     }
 
     pub fn signer(&self) -> Keypair {
         match self.keypair_filepath.clone() {
             Some(filepath) => read_keypair_file(filepath.clone())
-                .expect(format!("No keypair found at {}", filepath).as_str()),
-            None => panic!("No keypair provided"),
+                .unwrap_or_else(|_| exit_with_error(format!("No keypair found at {}", filepath))),
+            None => exit_with_error("No keypair provided".into()),
+        }
+    }
+
+    /// The authority of the proof to mine for, which is the signer unless configured otherwise.
+    pub fn authority(&self) -> Pubkey {
+        self.authority.unwrap_or_else(|| self.signer().pubkey())
+    }
+
+    /// The keypair that pays transaction fees and rent, which is the signer unless a separate
+    /// fee payer is configured.
+    pub fn fee_payer(&self) -> Keypair {
+        match self.fee_payer_filepath.clone() {
+            Some(filepath) => read_keypair_file(filepath.clone()).unwrap_or_else(|_| {
+                exit_with_error(format!("No fee payer keypair found at {}", filepath))
+            }),
+            None => self.signer(),
         }
     }
 }
+
+/// Reports a missing keypair the same way as other invalid input, rather than panicking.
+fn exit_with_error(msg: String) -> ! {
+    eprintln!("{} {}", "ERROR".bold().red(), msg);
+    std::process::exit(EXIT_PARSE);
+}
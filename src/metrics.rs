@@ -0,0 +1,442 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use solana_program::pubkey::Pubkey;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::{
+    error::{OreCliError, Result},
+    program_errors::FailedInstruction,
+    utils::amount_u64_to_string,
+};
+
+/// How long to wait before accepting connections again after a failed accept, in seconds.
+const ACCEPT_BACKOFF: u64 = 1;
+
+/// The upper bounds of the best difficulty histogram buckets.
+const DIFFICULTY_BUCKETS: [u32; 13] = [8, 10, 12, 14, 16, 18, 20, 22, 24, 26, 28, 30, 32];
+
+/// Counters and gauges from the mine loop, served in the Prometheus text format.
+#[derive(Default)]
+pub struct Metrics {
+    hashes: AtomicU64,
+    hashrates: Mutex<BTreeMap<usize, u64>>,
+    difficulty_buckets: [AtomicU64; DIFFICULTY_BUCKETS.len()],
+    difficulty_sum: AtomicU64,
+    difficulty_count: AtomicU64,
+    submissions_sent: AtomicU64,
+    submissions_landed: AtomicU64,
+    submissions_failed: Mutex<BTreeMap<String, u64>>,
+    priority_fees: AtomicU64,
+    sol_balances: Mutex<BTreeMap<Pubkey, u64>>,
+    stake_balances: Mutex<BTreeMap<Pubkey, u64>>,
+    rewards: Mutex<BTreeMap<Pubkey, u64>>,
+}
+
+impl Metrics {
+    /// Records the hashes each worker computed in one call to the pool.
+    pub fn record_hashes(&self, worker_hashes: &[u64], elapsed: Duration) {
+        let secs = elapsed.as_secs_f64().max(f64::EPSILON);
+        let mut hashrates = self.hashrates.lock().unwrap();
+        for (thread, hashes) in worker_hashes.iter().enumerate() {
+            self.hashes.fetch_add(*hashes, Ordering::Relaxed);
+            hashrates.insert(thread, (*hashes as f64 / secs) as u64);
+        }
+    }
+
+    /// Records the best difficulty of a round.
+    pub fn record_best_difficulty(&self, difficulty: u32) {
+        for (bucket, bound) in self.difficulty_buckets.iter().zip(DIFFICULTY_BUCKETS) {
+            if difficulty.le(&bound) {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.difficulty_sum
+            .fetch_add(difficulty as u64, Ordering::Relaxed);
+        self.difficulty_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_submission_sent(&self) {
+        self.submissions_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_submission_landed(&self) {
+        self.submissions_landed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_submission_failed(&self, err: &OreCliError) {
+        *self
+            .submissions_failed
+            .lock()
+            .unwrap()
+            .entry(error_kind(err))
+            .or_default() += 1;
+    }
+
+    /// Records the priority fee paid by a transaction that was processed. Only transactions this
+    /// client saw land are counted, so one that lands after the client stopped checking on it is
+    /// missed.
+    pub fn record_priority_fee(&self, microlamports_per_cu: u64, compute_unit_limit: u32) {
+        let lamports =
+            (microlamports_per_cu as u128 * compute_unit_limit as u128).div_ceil(1_000_000);
+        self.priority_fees
+            .fetch_add(lamports as u64, Ordering::Relaxed);
+    }
+
+    pub fn set_sol_balance(&self, address: Pubkey, lamports: u64) {
+        self.sol_balances.lock().unwrap().insert(address, lamports);
+    }
+
+    pub fn set_stake_balance(&self, authority: Pubkey, amount: u64) {
+        self.stake_balances
+            .lock()
+            .unwrap()
+            .insert(authority, amount);
+    }
+
+    pub fn record_rewards(&self, authority: Pubkey, amount: u64) {
+        *self.rewards.lock().unwrap().entry(authority).or_default() += amount;
+    }
+
+    /// Renders every metric in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        // Hashing
+        header(&mut out, "ore_hashes_total", "counter", "Hashes computed");
+        sample(
+            &mut out,
+            "ore_hashes_total",
+            &[],
+            self.hashes.load(Ordering::Relaxed),
+        );
+        header(
+            &mut out,
+            "ore_hashrate",
+            "gauge",
+            "Hashes per second of each thread in the latest hashing run",
+        );
+        for (thread, hashrate) in self.hashrates.lock().unwrap().iter() {
+            sample(
+                &mut out,
+                "ore_hashrate",
+                &[("thread", thread.to_string())],
+                *hashrate,
+            );
+        }
+        header(
+            &mut out,
+            "ore_best_difficulty",
+            "histogram",
+            "Best difficulty found each round",
+        );
+        for (bucket, bound) in self.difficulty_buckets.iter().zip(DIFFICULTY_BUCKETS) {
+            sample(
+                &mut out,
+                "ore_best_difficulty_bucket",
+                &[("le", bound.to_string())],
+                bucket.load(Ordering::Relaxed),
+            );
+        }
+        let count = self.difficulty_count.load(Ordering::Relaxed);
+        sample(
+            &mut out,
+            "ore_best_difficulty_bucket",
+            &[("le", "+Inf".into())],
+            count,
+        );
+        sample(
+            &mut out,
+            "ore_best_difficulty_sum",
+            &[],
+            self.difficulty_sum.load(Ordering::Relaxed),
+        );
+        sample(&mut out, "ore_best_difficulty_count", &[], count);
+
+        // Submissions
+        header(
+            &mut out,
+            "ore_submissions_sent_total",
+            "counter",
+            "Solutions sent",
+        );
+        sample(
+            &mut out,
+            "ore_submissions_sent_total",
+            &[],
+            self.submissions_sent.load(Ordering::Relaxed),
+        );
+        header(
+            &mut out,
+            "ore_submissions_landed_total",
+            "counter",
+            "Solutions landed",
+        );
+        sample(
+            &mut out,
+            "ore_submissions_landed_total",
+            &[],
+            self.submissions_landed.load(Ordering::Relaxed),
+        );
+        header(
+            &mut out,
+            "ore_submissions_failed_total",
+            "counter",
+            "Solutions that failed to land, by error kind",
+        );
+        for (kind, failed) in self.submissions_failed.lock().unwrap().iter() {
+            sample(
+                &mut out,
+                "ore_submissions_failed_total",
+                &[("kind", kind.to_string())],
+                *failed,
+            );
+        }
+        header(
+            &mut out,
+            "ore_priority_fees_lamports_total",
+            "counter",
+            "Priority fees paid by transactions seen to land, in lamports",
+        );
+        sample(
+            &mut out,
+            "ore_priority_fees_lamports_total",
+            &[],
+            self.priority_fees.load(Ordering::Relaxed),
+        );
+
+        // Balances
+        header(
+            &mut out,
+            "ore_sol_balance_lamports",
+            "gauge",
+            "SOL balance of the fee payer, in lamports",
+        );
+        for (address, lamports) in self.sol_balances.lock().unwrap().iter() {
+            sample(
+                &mut out,
+                "ore_sol_balance_lamports",
+                &[("address", address.to_string())],
+                *lamports,
+            );
+        }
+        header(
+            &mut out,
+            "ore_stake_balance",
+            "gauge",
+            "Stake balance of the proof, in ORE",
+        );
+        for (authority, amount) in self.stake_balances.lock().unwrap().iter() {
+            sample(
+                &mut out,
+                "ore_stake_balance",
+                &[("authority", authority.to_string())],
+                amount_u64_to_string(*amount),
+            );
+        }
+        header(
+            &mut out,
+            "ore_rewards_total",
+            "counter",
+            "Rewards earned by landed solutions, in ORE",
+        );
+        for (authority, amount) in self.rewards.lock().unwrap().iter() {
+            sample(
+                &mut out,
+                "ore_rewards_total",
+                &[("authority", authority.to_string())],
+                amount_u64_to_string(*amount),
+            );
+        }
+        out
+    }
+}
+
+/// Serves the metrics over http until the process exits.
+pub async fn serve(addr: SocketAddr, metrics: Arc<Metrics>) -> Result<()> {
+    let listener = TcpListener::bind(addr).await.map_err(|err| {
+        OreCliError::InvalidArgs(format!(
+            "Failed to serve metrics on {}: {}\nPick another --metrics-addr",
+            addr, err
+        ))
+    })?;
+    tokio::spawn(async move {
+        loop {
+            // Back off when out of file descriptors or the like, rather than spin
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(err) => {
+                    tracing::warn!(%err, "Failed to accept metrics connection");
+                    tokio::time::sleep(Duration::from_secs(ACCEPT_BACKOFF)).await;
+                    continue;
+                }
+            };
+            let metrics = metrics.clone();
+            tokio::spawn(async move {
+                if let Err(err) = respond(stream, &metrics).await {
+                    tracing::debug!(%err, "Failed to serve metrics");
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+/// Answers any request with the current metrics.
+async fn respond(mut stream: TcpStream, metrics: &Metrics) -> std::io::Result<()> {
+    let mut request = [0u8; 1024];
+    let _ = stream.read(&mut request).await?;
+    let body = metrics.render();
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// The kind of error a submission failed with, as a label.
+pub fn error_kind(err: &OreCliError) -> String {
+    match err {
+        OreCliError::Rpc(_) => "rpc".into(),
+        OreCliError::Parse(_) => "parse".into(),
+        OreCliError::InvalidArgs(_) => "invalid_args".into(),
+        OreCliError::AccountNotFound(_) => "account_not_found".into(),
+        OreCliError::InsufficientBalance { .. } => "insufficient_balance".into(),
+        OreCliError::NotLanded { .. } => "not_landed".into(),
+        OreCliError::UserAbort => "aborted".into(),
+        OreCliError::Program { failed, .. } => match failed {
+            Some(FailedInstruction {
+                known: Some(known), ..
+            }) => known.to_string().replace(' ', "_"),
+            _ => "program".into(),
+        },
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(out, "# HELP {} {}", name, help).ok();
+    writeln!(out, "# TYPE {} {}", name, kind).ok();
+}
+
+fn sample<T: std::fmt::Display>(out: &mut String, name: &str, labels: &[(&str, String)], value: T) {
+    let labels = labels
+        .iter()
+        .map(|(label, value)| format!("{}=\"{}\"", label, escape(value)))
+        .collect::<Vec<_>>()
+        .join(",");
+    if labels.is_empty() {
+        writeln!(out, "{} {}", name, value).ok();
+    } else {
+        writeln!(out, "{}{{{}}} {}", name, labels, value).ok();
+    }
+}
+
+/// Escapes a label value, as the text format requires of backslashes, quotes, and newlines.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The value of the sample with exactly this name and labels.
+    fn value(rendered: &str, series: &str) -> Option<u64> {
+        rendered
+            .lines()
+            .find_map(|line| line.strip_prefix(series)?.strip_prefix(' '))
+            .and_then(|value| value.parse().ok())
+    }
+
+    #[test]
+    fn difficulty_buckets_are_cumulative() {
+        let metrics = Metrics::default();
+        for difficulty in [9, 15, 40] {
+            metrics.record_best_difficulty(difficulty);
+        }
+        let rendered = metrics.render();
+        let bucket = |le: &str| {
+            value(
+                &rendered,
+                &format!("ore_best_difficulty_bucket{{le=\"{}\"}}", le),
+            )
+        };
+        assert_eq!(bucket("8"), Some(0));
+        assert_eq!(bucket("10"), Some(1));
+        assert_eq!(bucket("14"), Some(1));
+        assert_eq!(bucket("16"), Some(2));
+        assert_eq!(bucket("32"), Some(2));
+        assert_eq!(bucket("+Inf"), Some(3));
+        assert_eq!(value(&rendered, "ore_best_difficulty_count"), Some(3));
+        assert_eq!(value(&rendered, "ore_best_difficulty_sum"), Some(64));
+
+        // Every bucket holds at least the ones below it
+        let counts: Vec<u64> = DIFFICULTY_BUCKETS
+            .iter()
+            .map(|bound| bucket(&bound.to_string()).unwrap())
+            .collect();
+        assert!(counts.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn hashes_and_failures() {
+        let metrics = Metrics::default();
+        metrics.record_hashes(&[100, 200], Duration::from_secs(2));
+        metrics.record_hashes(&[50, 50], Duration::from_secs(1));
+        metrics.record_submission_failed(&OreCliError::Parse("bad".into()));
+        metrics.record_submission_failed(&OreCliError::Parse("worse".into()));
+        metrics.record_submission_failed(&OreCliError::NotLanded { attempts: 3 });
+        let rendered = metrics.render();
+        assert_eq!(value(&rendered, "ore_hashes_total"), Some(400));
+        assert_eq!(value(&rendered, "ore_hashrate{thread=\"0\"}"), Some(50));
+        assert_eq!(value(&rendered, "ore_hashrate{thread=\"1\"}"), Some(50));
+        assert_eq!(
+            value(&rendered, "ore_submissions_failed_total{kind=\"parse\"}"),
+            Some(2)
+        );
+        assert_eq!(
+            value(
+                &rendered,
+                "ore_submissions_failed_total{kind=\"not_landed\"}"
+            ),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn label_values_are_escaped() {
+        let metrics = Metrics::default();
+        metrics
+            .submissions_failed
+            .lock()
+            .unwrap()
+            .insert("a\"b\\c\nd".into(), 1);
+        let rendered = metrics.render();
+        assert_eq!(
+            value(
+                &rendered,
+                "ore_submissions_failed_total{kind=\"a\\\"b\\\\c\\nd\"}"
+            ),
+            Some(1)
+        );
+
+        // The newline must not split the sample across lines
+        assert!(!rendered.lines().any(|line| line.starts_with("d\"")));
+    }
+}
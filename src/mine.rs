@@ -1,59 +1,249 @@
-use std::{sync::Arc, time::Instant};
+use std::{
+    ops::Range,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use colored::*;
-use drillx::{
-    equix::{self},
-    Hash, Solution,
-};
+use drillx::Solution;
 use ore_api::{
-    consts::{BUS_ADDRESSES, BUS_COUNT, EPOCH_DURATION},
+    consts::{BUS_ADDRESSES, EPOCH_DURATION, ONE_MINUTE, TOLERANCE},
+    error::OreError,
     state::{Config, Proof},
 };
-use rand::Rng;
 use solana_program::pubkey::Pubkey;
-use solana_rpc_client::spinner;
-use solana_sdk::signer::Signer;
+use solana_sdk::signer::{keypair::Keypair, Signer};
+use tracing::{Instrument, Span};
 
 use crate::{
+    adaptive_buffer::AdaptiveBuffer,
     args::MineArgs,
+    bus_strategy::expected_reward,
+    error::{OreCliError, Result},
+    fleet::read_keypair_filepaths,
+    metrics::{self, error_kind},
+    mining_pool::MiningPool,
+    nonce_allocator::{partition, NonceAllocator},
+    priority_fee::PriorityFee,
+    program_errors::{FailedInstruction, KnownError},
     send_and_confirm::ComputeBudget,
-    utils::{amount_u64_to_string, get_clock, get_config, get_proof_with_authority},
+    utils::{amount_u64_to_string, get_config, get_proof_with_authority, parse_pubkey},
     Miner,
 };
 
+/// The delay before retrying a mining round that failed on an rpc error.
+const ROUND_RETRY_DELAY: u64 = 5;
+
 impl Miner {
-    pub async fn mine(&self, args: MineArgs) {
-        // Register, if needed.
-        let signer = self.signer();
-        self.open().await;
+    pub async fn mine(&self, mut args: MineArgs) -> Result<()> {
+        // Pick the nonces this machine is responsible for
+        let nonces = match (
+            args.nonce_range.clone(),
+            args.worker_index,
+            args.worker_count,
+        ) {
+            (Some(range), _, _) => range,
+            (None, Some(index), Some(count)) => {
+                if index >= count {
+                    return Err(OreCliError::InvalidArgs(format!(
+                        "Worker index ({}) must be less than worker count ({})",
+                        index, count
+                    )));
+                }
+                partition(index, count)
+            }
+            _ => 0..u64::MAX,
+        };
+
+        // Check the auto-claim wallet before mining
+        let auto_claim_to = args
+            .auto_claim_to
+            .as_deref()
+            .map(parse_pubkey)
+            .transpose()?;
+
+        // Claiming into a wallet that compounds would stake the claim straight back
+        if args.compound && args.auto_claim_threshold.is_some() {
+            let signers = match &args.keypairs {
+                Some(keypairs) => read_keypair_filepaths(keypairs)?
+                    .into_iter()
+                    .map(|filepath| self.with_keypair(filepath).signer().pubkey())
+                    .collect(),
+                None => vec![self.signer().pubkey()],
+            };
+            let claims_to_signer = auto_claim_to.map_or(true, |to| signers.contains(&to));
+            if claims_to_signer {
+                return Err(OreCliError::InvalidArgs(
+                    "--auto-claim-threshold with --compound needs --auto-claim-to set to another wallet".into(),
+                ));
+            }
+        }
+
+        // Check difficulty bounds
+        args.min_difficulty = args.min_difficulty.max(ore_api::consts::MIN_DIFFICULTY);
+        if let Some(target_difficulty) = args.target_difficulty {
+            if target_difficulty < args.min_difficulty {
+                return Err(OreCliError::InvalidArgs(format!(
+                    "Target difficulty ({}) must be at least the min difficulty ({})",
+                    target_difficulty, args.min_difficulty
+                )));
+            }
+        }
+
+        // Serve metrics, if requested
+        if let Some(addr) = args.metrics_addr {
+            metrics::serve(addr, self.metrics.clone()).await?;
+        }
+
+        // Start the worker pool
+        let mut pool = MiningPool::new(args.threads);
+        let buffer = AdaptiveBuffer::new();
+
+        // Show the dashboard until mining stops, if requested
+        let _dashboard = if args.tui {
+            Some(self.start_dashboard()?)
+        } else {
+            None
+        };
 
         // Check num threads
         self.check_num_cores(args.threads);
 
+        // Mine with many keypairs, if requested
+        if let Some(keypairs) = args.keypairs.clone() {
+            return self
+                .mine_fleet(
+                    &keypairs,
+                    Arc::new(args),
+                    &mut pool,
+                    Arc::new(buffer),
+                    nonces,
+                )
+                .await;
+        }
+
+        // Register, if needed, or check the keypair may hash for the configured authority
+        let signer = self.signer();
+        let authority = self.authority();
+        if authority.eq(&signer.pubkey()) {
+            self.register().await?;
+        } else {
+            if args.compound || args.auto_claim_threshold.is_some() {
+                return Err(OreCliError::InvalidArgs(
+                    "Only the proof's authority can claim or compound its stake".into(),
+                ));
+            }
+            let proof = get_proof_with_authority(&self.rpc_client, authority).await?;
+            if proof.miner.ne(&signer.pubkey()) {
+                return Err(OreCliError::InvalidArgs(format!(
+                    "Keypair {} is not the miner of the proof for {}",
+                    signer.pubkey(),
+                    authority
+                )));
+            }
+        }
+
         // Start mining loop
         loop {
-            // Fetch proof
-            let proof = get_proof_with_authority(&self.rpc_client, signer.pubkey()).await;
-            println!(
-                "\nStake balance: {} ORE",
-                amount_u64_to_string(proof.balance)
-            );
+            match self
+                .mine_round(&signer, &args, &mut pool, &buffer, nonces.clone())
+                .await
+            {
+                Ok(()) => {}
+                Err(err @ OreCliError::InsufficientBalance { .. }) => return Err(err),
+                Err(err) => {
+                    tracing::error!(%err, "Mining round failed");
+                    self.print(format!("{} {}", "ERROR".bold().red(), err));
+                    tokio::time::sleep(Duration::from_secs(ROUND_RETRY_DELAY)).await;
+                }
+            }
+        }
+    }
+
+    async fn mine_round(
+        &self,
+        signer: &Keypair,
+        args: &MineArgs,
+        pool: &mut MiningPool,
+        buffer: &AdaptiveBuffer,
+        nonces: Range<u64>,
+    ) -> Result<()> {
+        // Fetch proof
+        let proof = get_proof_with_authority(&self.rpc_client, self.authority()).await?;
+        self.metrics
+            .set_stake_balance(self.authority(), proof.balance);
+        self.print(format!(
+            "\nStake balance: {} ORE",
+            amount_u64_to_string(proof.balance)
+        ));
 
+        // Log the round's events under its challenge
+        async {
             // Calc cutoff time
-            let cutoff_time = self.get_cutoff(proof, args.buffer_time).await;
+            let buffer_time = args.buffer_time.unwrap_or_else(|| buffer.buffer_time());
+            let cutoff_time = self.get_cutoff(proof, buffer_time).await?;
 
             // Run drillx
-            let solution = Self::find_hash_par(proof, cutoff_time, args.threads).await;
+            let (solution, difficulty) = self
+                .find_hash_par(
+                    pool,
+                    proof,
+                    cutoff_time,
+                    args.min_difficulty,
+                    args.target_difficulty,
+                    nonces,
+                )
+                .await;
+
+            // Hold a hash found early until the program will accept it
+            self.wait_for_spam_window(proof).await?;
 
             // Submit most difficult hash
-            let config = get_config(&self.rpc_client).await;
+            self.submit(signer, args, proof, solution, difficulty, buffer)
+                .await?;
+
+            // Claim and stake rewards, if configured
+            self.auto_claim_after_round(args).await?;
+            self.compound_after_round(args).await
+        }
+        .instrument(round_span(self.authority(), proof))
+        .await
+    }
+
+    /// Submits a solution, resubmitting while the proof is still on the solution's challenge.
+    pub async fn submit(
+        &self,
+        signer: &Keypair,
+        args: &MineArgs,
+        proof: Proof,
+        solution: Solution,
+        difficulty: u32,
+        buffer: &AdaptiveBuffer,
+    ) -> Result<()> {
+        let authority = self.authority();
+        let reward = expected_reward(
+            get_config(&self.rpc_client).await?,
+            proof,
+            difficulty,
+            self.clock.now().await?,
+        );
+        let mut resubmits = 0;
+        loop {
+            // Refetch the config on every attempt, so a resend sees the latest epoch reset
+            let config = get_config(&self.rpc_client).await?;
             let mut compute_budget = 500_000;
             let mut ixs = vec![];
-            if self.should_reset(config).await {
+            let resetting = self.should_reset(config).await?;
+            if resetting {
                 compute_budget += 100_000;
                 ixs.push(ore_api::instruction::reset(signer.pubkey()));
             }
-            if self.should_crown(config, proof).await {
+
+            // Pick the bus once the reset is known, so it sees the balances it will pay from
+            let bus = self.find_bus(args.bus_strategy, reward, resetting).await?;
+
+            // Only the proof's authority can crown it
+            if authority.eq(&signer.pubkey()) && self.should_crown(config, proof).await? {
                 compute_budget += 250_000;
                 ixs.push(ore_api::instruction::crown(
                     signer.pubkey(),
@@ -62,86 +252,153 @@ impl Miner {
             }
             ixs.push(ore_api::instruction::mine(
                 signer.pubkey(),
-                signer.pubkey(),
-                find_bus(),
+                authority,
+                BUS_ADDRESSES[bus],
                 solution,
             ));
-            self.send_and_confirm(&ixs, ComputeBudget::Fixed(compute_budget), false)
-                .await
-                .ok();
-        }
-    }
 
-    async fn find_hash_par(proof: Proof, cutoff_time: u64, threads: u64) -> Solution {
-        // Dispatch job to each thread
-        let progress_bar = Arc::new(spinner::new_progress_bar());
-        progress_bar.set_message("Mining...");
-        let handles: Vec<_> = (0..threads)
-            .map(|i| {
-                std::thread::spawn({
-                    let proof = proof.clone();
-                    let progress_bar = progress_bar.clone();
-                    let mut memory = equix::SolverMemory::new();
-                    move || {
-                        let timer = Instant::now();
-                        let mut nonce = u64::MAX.saturating_div(threads).saturating_mul(i);
-                        let mut best_nonce = nonce;
-                        let mut best_difficulty = 0;
-                        let mut best_hash = Hash::default();
-                        loop {
-                            // Create hash
-                            if let Ok(hx) = drillx::hash_with_memory(
-                                &mut memory,
-                                &proof.challenge,
-                                &nonce.to_le_bytes(),
-                            ) {
-                                let difficulty = hx.difficulty();
-                                if difficulty.gt(&best_difficulty) {
-                                    best_nonce = nonce;
-                                    best_difficulty = difficulty;
-                                    best_hash = hx;
-                                }
-                            }
-
-                            // Exit if time has elapsed
-                            if nonce % 100 == 0 {
-                                if timer.elapsed().as_secs().ge(&cutoff_time) {
-                                    if best_difficulty.gt(&ore_api::consts::MIN_DIFFICULTY) {
-                                        // Mine until min difficulty has been met
-                                        break;
-                                    }
-                                } else if i == 0 {
-                                    progress_bar.set_message(format!(
-                                        "Mining... ({} sec remaining)",
-                                        cutoff_time.saturating_sub(timer.elapsed().as_secs()),
-                                    ));
-                                }
-                            }
-
-                            // Increment nonce
-                            nonce += 1;
-                        }
-
-                        // Return the best nonce
-                        (best_nonce, best_difficulty, best_hash)
+            // Raise the fee on each resubmission, if configured, keeping an auto fee under the max
+            let priority_fee = self.get_priority_fee(&ixs).await;
+            let bumped_fee = priority_fee.saturating_add(
+                priority_fee.saturating_mul(args.resubmit_fee_bump.saturating_mul(resubmits)) / 100,
+            );
+            let priority_fee = match self.priority_fee {
+                PriorityFee::Auto => bumped_fee.min(self.max_priority_fee.max(priority_fee)),
+                PriorityFee::Fixed(_) => bumped_fee,
+            };
+
+            // Time each attempt on its own, so retries do not inflate the landing latency
+            let timer = Instant::now();
+
+            // Count the submission by outcome
+            self.metrics.record_submission_sent();
+            let res = self
+                .send_and_confirm_with_priority_fee(
+                    &ixs,
+                    ComputeBudget::Fixed(compute_budget),
+                    false,
+                    priority_fee,
+                )
+                .await;
+            let latency = timer.elapsed();
+            match &res {
+                Ok(sig) => {
+                    self.metrics.record_submission_landed();
+                    self.dashboard
+                        .record_submission(difficulty, "landed".into(), Some(*sig));
+                }
+                Err(err) => {
+                    self.metrics.record_submission_failed(err);
+                    self.dashboard
+                        .record_submission(difficulty, error_kind(err), None);
+                }
+            }
+
+            // Submission errors are reported by the spinner, only a lack of funds stops mining
+            match res {
+                Err(err @ OreCliError::InsufficientBalance { .. }) => {
+                    tracing::error!(bus, priority_fee, outcome = "insufficient_balance", %err);
+                    return Err(err);
+                }
+                Err(err) if should_resubmit(&err) => {
+                    // Resubmit the same solution while the proof is still on this challenge
+                    let current = get_proof_with_authority(&self.rpc_client, authority).await?;
+                    if current.challenge.ne(&proof.challenge)
+                        || current.last_hash_at.ne(&proof.last_hash_at)
+                    {
+                        tracing::warn!(bus, priority_fee, outcome = "stale", %err);
+                        return Ok(());
+                    }
+
+                    // A reset can only be sent once the epoch is over
+                    if needs_reset(&err) {
+                        let config = get_config(&self.rpc_client).await?;
+                        let wait = config
+                            .last_reset_at
+                            .saturating_add(EPOCH_DURATION)
+                            .saturating_sub(self.clock.now().await?)
+                            .max(0);
+                        tokio::time::sleep(Duration::from_secs(wait as u64)).await;
                     }
-                })
-            })
-            .collect();
-
-        // Join handles and return best nonce
-        let mut best_nonce = 0;
-        let mut best_difficulty = 0;
-        let mut best_hash = Hash::default();
-        for h in handles {
-            if let Ok((nonce, difficulty, hash)) = h.join() {
-                if difficulty > best_difficulty {
-                    best_difficulty = difficulty;
-                    best_nonce = nonce;
-                    best_hash = hash;
+                    resubmits += 1;
+                    tracing::warn!(
+                        bus,
+                        priority_fee,
+                        outcome = "resubmit",
+                        attempt = resubmits,
+                        %err
+                    );
+                    self.print(format!(
+                        "Solution did not land, resubmitting (attempt {})...",
+                        resubmits
+                    ));
+                }
+                Ok(sig) => {
+                    // Track how long the solution took to land, for the adaptive buffer time
+                    let landed = get_proof_with_authority(&self.rpc_client, authority).await?;
+                    self.metrics
+                        .record_rewards(authority, landed.balance.saturating_sub(proof.balance));
+                    self.metrics.set_stake_balance(authority, landed.balance);
+                    self.dashboard.set_stake_balance(landed.balance);
+
+                    // The program still counts a hash a tolerance past the minute as on time
+                    let window_end = proof.last_hash_at.saturating_add(ONE_MINUTE + TOLERANCE);
+                    let landed_in_window = landed.last_hash_at.le(&window_end);
+                    buffer.record(latency, landed_in_window);
+                    tracing::info!(
+                        bus,
+                        priority_fee,
+                        difficulty,
+                        signature = %sig,
+                        latency_ms = latency.as_millis() as u64,
+                        landed_in_window,
+                        outcome = "landed",
+                        "Solution landed"
+                    );
+                    return Ok(());
+                }
+                Err(err) => {
+                    tracing::warn!(bus, priority_fee, outcome = "failed", %err);
+                    return Ok(());
                 }
             }
         }
+    }
+
+    async fn find_hash_par(
+        &self,
+        pool: &mut MiningPool,
+        proof: Proof,
+        cutoff_time: u64,
+        min_difficulty: u32,
+        target_difficulty: Option<u32>,
+        nonces: Range<u64>,
+    ) -> (Solution, u32) {
+        // Dispatch job to the worker pool
+        let timer = Instant::now();
+        self.dashboard.start_round(
+            self.authority(),
+            proof.balance,
+            timer + Duration::from_secs(cutoff_time),
+        );
+        let progress_bar = self.progress_bar();
+        progress_bar.set_message("Mining...");
+        let (solution, best_hash, best_difficulty) = pool
+            .find_hash(
+                proof.challenge,
+                cutoff_time,
+                min_difficulty,
+                target_difficulty,
+                Arc::new(NonceAllocator::new(nonces)),
+                |remaining, progress| {
+                    self.dashboard.record_progress(&progress);
+                    progress_bar.set_message(format!(
+                        "Mining... ({} sec remaining, best difficulty: {}, {} H/sec)",
+                        remaining, progress.best_difficulty, progress.hashrate
+                    ));
+                },
+            )
+            .await;
 
         // Update log
         progress_bar.finish_with_message(format!(
@@ -149,49 +406,100 @@ impl Miner {
             bs58::encode(best_hash.h).into_string(),
             best_difficulty
         ));
+        tracing::info!(
+            best_difficulty,
+            hashes = pool.hashes(),
+            hash = %bs58::encode(best_hash.h).into_string(),
+            "Hashing finished"
+        );
+        self.metrics
+            .record_hashes(&pool.worker_hashes(), timer.elapsed());
+        self.metrics.record_best_difficulty(best_difficulty);
 
-        Solution::new(best_hash.d, best_nonce.to_le_bytes())
+        (solution, best_difficulty)
     }
 
     pub fn check_num_cores(&self, threads: u64) {
         // Check num threads
         let num_cores = num_cpus::get() as u64;
         if threads.gt(&num_cores) {
-            println!(
+            self.print(format!(
                 "{} Number of threads ({}) exceeds available cores ({})",
                 "WARNING".bold().yellow(),
                 threads,
                 num_cores
-            );
+            ));
         }
     }
 
-    async fn should_crown(&self, config: Config, proof: Proof) -> bool {
-        proof.balance.gt(&config.max_stake)
+    async fn should_crown(&self, config: Config, proof: Proof) -> Result<bool> {
+        // The program rejects crowns for proofs that staked within the last minute
+        let now = self.clock.now().await?;
+        Ok(proof.balance.gt(&config.max_stake)
+            && proof.last_stake_at.saturating_add(ONE_MINUTE).le(&now))
     }
 
-    async fn should_reset(&self, config: Config) -> bool {
-        let clock = get_clock(&self.rpc_client).await;
-        config
+    async fn should_reset(&self, config: Config) -> Result<bool> {
+        let now = self.clock.now().await?;
+        Ok(config
             .last_reset_at
             .saturating_add(EPOCH_DURATION)
             .saturating_sub(5) // Buffer
-            .le(&clock.unix_timestamp)
+            .le(&now))
     }
 
-    async fn get_cutoff(&self, proof: Proof, buffer_time: u64) -> u64 {
-        let clock = get_clock(&self.rpc_client).await;
-        proof
+    /// Holds a solution found before the cutoff, as when the target difficulty is reached
+    /// early, until the program stops rejecting it as spam, a tolerance before the full minute.
+    pub async fn wait_for_spam_window(&self, proof: Proof) -> Result<()> {
+        let wait = self.get_cutoff(proof, TOLERANCE as u64).await?;
+        if wait.eq(&0) {
+            return Ok(());
+        }
+        self.print(format!(
+            "Waiting {} sec for the proof's next hash window...",
+            wait
+        ));
+        tokio::time::sleep(Duration::from_secs(wait)).await;
+        Ok(())
+    }
+
+    pub async fn get_cutoff(&self, proof: Proof, buffer_time: u64) -> Result<u64> {
+        let now = self.clock.now().await?;
+        Ok(proof
             .last_hash_at
             .saturating_add(60)
             .saturating_sub(buffer_time as i64)
-            .saturating_sub(clock.unix_timestamp)
-            .max(0) as u64
+            .saturating_sub(now)
+            .max(0) as u64)
     }
 }
 
-// TODO Pick a better strategy (avoid draining bus)
-fn find_bus() -> Pubkey {
-    let i = rand::thread_rng().gen_range(0..BUS_COUNT);
-    BUS_ADDRESSES[i]
+/// The span to log a mining round's events in.
+pub fn round_span(authority: Pubkey, proof: Proof) -> Span {
+    tracing::info_span!(
+        "round",
+        %authority,
+        challenge = %bs58::encode(proof.challenge).into_string()
+    )
+}
+
+/// Whether a failed submission may still land if the same solution is sent again.
+///
+/// Spam is not retried, since the solution is already held until the spam window opens.
+fn should_resubmit(err: &OreCliError) -> bool {
+    matches!(err, OreCliError::Rpc(_) | OreCliError::NotLanded { .. }) || needs_reset(err)
+}
+
+/// Whether a submission failed because the epoch has not been reset.
+fn needs_reset(err: &OreCliError) -> bool {
+    matches!(
+        err,
+        OreCliError::Program {
+            failed: Some(FailedInstruction {
+                known: Some(KnownError::Ore(OreError::NeedsReset)),
+                ..
+            }),
+            ..
+        }
+    )
 }
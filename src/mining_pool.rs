@@ -0,0 +1,222 @@
+use std::{
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        mpsc, Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use drillx::{equix, Hash, Solution};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use crate::nonce_allocator::NonceAllocator;
+
+/// How often the progress of a round is polled, in milliseconds.
+const POLL_INTERVAL: u64 = 250;
+
+/// A challenge to hash until the deadline and the minimum difficulty are met, or until the
+/// target difficulty is reached.
+#[derive(Clone)]
+struct Job {
+    challenge: [u8; 32],
+    deadline: Instant,
+    min_difficulty: u32,
+    target_difficulty: Option<u32>,
+    nonces: Arc<NonceAllocator>,
+}
+
+/// The best hash a worker found for a job.
+struct WorkerResult {
+    nonce: u64,
+    difficulty: u32,
+    hash: Hash,
+}
+
+/// State shared between the workers and the mine loop.
+pub struct PoolState {
+    pub best_difficulty: AtomicU32,
+    pub hashes: AtomicU64,
+    pub worker_hashes: Vec<AtomicU64>,
+}
+
+/// Live progress of the current round.
+pub struct RoundProgress {
+    pub best_difficulty: u32,
+    pub hashrate: u64,
+    pub thread_hashrates: Vec<u64>,
+}
+
+/// A set of long-lived hashing threads that each keep their solver memory across rounds.
+pub struct MiningPool {
+    jobs: Vec<mpsc::Sender<Job>>,
+    results: UnboundedReceiver<WorkerResult>,
+    state: Arc<PoolState>,
+}
+
+impl MiningPool {
+    pub fn new(threads: u64) -> Self {
+        let state = Arc::new(PoolState {
+            best_difficulty: AtomicU32::new(0),
+            hashes: AtomicU64::new(0),
+            worker_hashes: (0..threads).map(|_| AtomicU64::new(0)).collect(),
+        });
+        let (results_tx, results) = unbounded_channel();
+        let jobs = (0..threads)
+            .map(|i| {
+                let (jobs_tx, jobs_rx) = mpsc::channel();
+                let state = state.clone();
+                let results_tx = results_tx.clone();
+                std::thread::spawn(move || worker(i as usize, jobs_rx, results_tx, state));
+                jobs_tx
+            })
+            .collect();
+        Self {
+            jobs,
+            results,
+            state,
+        }
+    }
+
+    /// The number of hashes computed in the latest call to find_hash.
+    pub fn hashes(&self) -> u64 {
+        self.state.hashes.load(Ordering::Relaxed)
+    }
+
+    /// The number of hashes each worker computed in the latest call to find_hash.
+    pub fn worker_hashes(&self) -> Vec<u64> {
+        self.state
+            .worker_hashes
+            .iter()
+            .map(|hashes| hashes.load(Ordering::Relaxed))
+            .collect()
+    }
+
+    /// Hashes the challenge on every worker and returns the best solution found.
+    pub async fn find_hash(
+        &mut self,
+        challenge: [u8; 32],
+        cutoff_time: u64,
+        min_difficulty: u32,
+        target_difficulty: Option<u32>,
+        nonces: Arc<NonceAllocator>,
+        mut on_progress: impl FnMut(u64, RoundProgress),
+    ) -> (Solution, Hash, u32) {
+        // Dispatch job to each worker
+        let timer = Instant::now();
+        let job = Job {
+            challenge,
+            deadline: timer + Duration::from_secs(cutoff_time),
+            min_difficulty,
+            target_difficulty,
+            nonces,
+        };
+        self.state.best_difficulty.store(0, Ordering::Relaxed);
+        self.state.hashes.store(0, Ordering::Relaxed);
+        for hashes in self.state.worker_hashes.iter() {
+            hashes.store(0, Ordering::Relaxed);
+        }
+        let mut pending = 0;
+        for jobs in self.jobs.iter() {
+            if jobs.send(job.clone()).is_ok() {
+                pending += 1;
+            }
+        }
+
+        // Collect results, reporting progress while the workers are busy
+        let mut best_nonce = 0;
+        let mut best_difficulty = 0;
+        let mut best_hash = Hash::default();
+        while pending > 0 {
+            match tokio::time::timeout(Duration::from_millis(POLL_INTERVAL), self.results.recv())
+                .await
+            {
+                Ok(Some(res)) => {
+                    pending -= 1;
+                    if res.difficulty > best_difficulty {
+                        best_nonce = res.nonce;
+                        best_difficulty = res.difficulty;
+                        best_hash = res.hash;
+                    }
+                }
+                Ok(None) => break,
+                Err(_) => {
+                    let elapsed = timer.elapsed();
+                    let rate = |hashes: &AtomicU64| {
+                        (hashes.load(Ordering::Relaxed) as f64 / elapsed.as_secs_f64().max(1.0))
+                            as u64
+                    };
+                    on_progress(
+                        cutoff_time.saturating_sub(elapsed.as_secs()),
+                        RoundProgress {
+                            best_difficulty: self.state.best_difficulty.load(Ordering::Relaxed),
+                            hashrate: rate(&self.state.hashes),
+                            thread_hashrates: self.state.worker_hashes.iter().map(rate).collect(),
+                        },
+                    );
+                }
+            }
+        }
+
+        (
+            Solution::new(best_hash.d, best_nonce.to_le_bytes()),
+            best_hash,
+            best_difficulty,
+        )
+    }
+}
+
+fn worker(
+    index: usize,
+    jobs: mpsc::Receiver<Job>,
+    results: UnboundedSender<WorkerResult>,
+    state: Arc<PoolState>,
+) {
+    let mut memory = equix::SolverMemory::new();
+    while let Ok(job) = jobs.recv() {
+        let mut best_nonce = 0;
+        let mut best_difficulty = 0;
+        let mut best_hash = Hash::default();
+        while let Some(chunk) = job.nonces.next_chunk() {
+            let chunk_size = chunk.end - chunk.start;
+            for nonce in chunk {
+                // Create hash
+                if let Ok(hx) =
+                    drillx::hash_with_memory(&mut memory, &job.challenge, &nonce.to_le_bytes())
+                {
+                    let difficulty = hx.difficulty();
+                    if difficulty.gt(&best_difficulty) {
+                        best_nonce = nonce;
+                        best_difficulty = difficulty;
+                        best_hash = hx;
+                        state
+                            .best_difficulty
+                            .fetch_max(difficulty, Ordering::Relaxed);
+                    }
+                }
+            }
+
+            // Exit if any worker has reached the target difficulty, or if time has elapsed and
+            // any worker has met the min difficulty
+            state.hashes.fetch_add(chunk_size, Ordering::Relaxed);
+            state.worker_hashes[index].fetch_add(chunk_size, Ordering::Relaxed);
+            let difficulty = state.best_difficulty.load(Ordering::Relaxed);
+            if job.target_difficulty.is_some_and(|t| difficulty.ge(&t))
+                || (Instant::now().ge(&job.deadline) && difficulty.ge(&job.min_difficulty))
+            {
+                break;
+            }
+        }
+
+        // Return the best nonce
+        if results
+            .send(WorkerResult {
+                nonce: best_nonce,
+                difficulty: best_difficulty,
+                hash: best_hash,
+            })
+            .is_err()
+        {
+            break;
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::{
+    ops::Range,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// The number of nonces handed to a worker at a time.
+pub const NONCE_CHUNK_SIZE: u64 = 100;
+
+/// Hands out disjoint chunks of a nonce range to workers on demand.
+pub struct NonceAllocator {
+    next: AtomicU64,
+    end: u64,
+}
+
+impl NonceAllocator {
+    pub fn new(range: Range<u64>) -> Self {
+        Self {
+            next: AtomicU64::new(range.start),
+            end: range.end,
+        }
+    }
+
+    /// Claims the next chunk of nonces, or returns none if the range is exhausted.
+    pub fn next_chunk(&self) -> Option<Range<u64>> {
+        let end = self.end;
+        self.next
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |next| {
+                (next < end).then(|| next.saturating_add(NONCE_CHUNK_SIZE).min(end))
+            })
+            .ok()
+            .map(|start| start..start.saturating_add(NONCE_CHUNK_SIZE).min(end))
+    }
+}
+
+/// Returns the slice of the nonce space assigned to one of several machines.
+pub fn partition(worker_index: u64, worker_count: u64) -> Range<u64> {
+    let size = u64::MAX / worker_count;
+    let start = size * worker_index;
+    if worker_index + 1 == worker_count {
+        start..u64::MAX
+    } else {
+        start..start + size
+    }
+}
+
+pub fn parse_nonce_range(s: &str) -> Result<Range<u64>, String> {
+    let (start, end) = s
+        .split_once("..")
+        .ok_or_else(|| format!("expected a range like START..END, got {}", s))?;
+    let start = start
+        .parse::<u64>()
+        .map_err(|_| format!("invalid range start: {}", start))?;
+    let end = end
+        .parse::<u64>()
+        .map_err(|_| format!("invalid range end: {}", end))?;
+    if start >= end {
+        return Err(format!("range start must be less than end, got {}", s));
+    }
+    Ok(start..end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Drains an allocator and checks its chunks tile the range in order.
+    fn assert_chunks_tile(range: Range<u64>) {
+        let allocator = NonceAllocator::new(range.clone());
+        let mut next = range.start;
+        while let Some(chunk) = allocator.next_chunk() {
+            assert_eq!(chunk.start, next);
+            assert!(chunk.end > chunk.start);
+            assert!(chunk.end - chunk.start <= NONCE_CHUNK_SIZE);
+            next = chunk.end;
+        }
+        assert_eq!(next, range.end);
+        assert_eq!(allocator.next_chunk(), None);
+    }
+
+    #[test]
+    fn chunks_are_contiguous_up_to_the_end() {
+        assert_chunks_tile(0..NONCE_CHUNK_SIZE * 3);
+        assert_chunks_tile(5..NONCE_CHUNK_SIZE * 3 + 7);
+        assert_chunks_tile(10..11);
+        assert_chunks_tile(u64::MAX - NONCE_CHUNK_SIZE * 2 - 1..u64::MAX);
+    }
+
+    #[test]
+    fn chunks_are_disjoint_across_threads() {
+        let allocator = NonceAllocator::new(0..NONCE_CHUNK_SIZE * 1_000 + 1);
+        let mut chunks: Vec<Range<u64>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut chunks = vec![];
+                        while let Some(chunk) = allocator.next_chunk() {
+                            chunks.push(chunk);
+                        }
+                        chunks
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        });
+        chunks.sort_by_key(|chunk| chunk.start);
+        let mut next = 0;
+        for chunk in chunks {
+            assert_eq!(chunk.start, next);
+            next = chunk.end;
+        }
+        assert_eq!(next, NONCE_CHUNK_SIZE * 1_000 + 1);
+    }
+
+    proptest! {
+        #[test]
+        fn partitions_cover_the_nonce_space(worker_count in 1..10_000u64) {
+            let mut next = 0;
+            for worker_index in 0..worker_count {
+                let range = partition(worker_index, worker_count);
+                prop_assert_eq!(range.start, next);
+                prop_assert!(range.end > range.start);
+                next = range.end;
+            }
+            prop_assert_eq!(next, u64::MAX);
+        }
+    }
+}
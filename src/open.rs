@@ -1,21 +1,64 @@
+use solana_program::pubkey::Pubkey;
 use solana_sdk::signature::Signer;
 
-use crate::{send_and_confirm::ComputeBudget, utils::proof_pubkey, Miner};
+use crate::{
+    args::OpenArgs,
+    error::{OreCliError, Result},
+    send_and_confirm::ComputeBudget,
+    utils::{get_proof_with_authority, proof_pubkey},
+    Miner,
+};
 
 impl Miner {
-    pub async fn open(&self) {
+    pub async fn open(&self, args: OpenArgs) -> Result<()> {
+        // Default to the signer hashing for its own proof
+        let signer = self.signer();
+        let miner = args.miner.unwrap_or(signer.pubkey());
+
+        // Open the proof, paying rent from the given payer
+        match args.payer {
+            Some(payer) => self.with_fee_payer(payer).open_proof(miner).await?,
+            None => self.open_proof(miner).await?,
+        }
+
+        // A proof that already existed keeps its miner, so do not report it as changed
+        let proof = get_proof_with_authority(&self.rpc_client, signer.pubkey()).await?;
+        if proof.miner.ne(&miner) {
+            return Err(OreCliError::InvalidArgs(format!(
+                "Proof {} already exists with miner {}, not {}\nClose it with `ore close` and open it again to change its miner",
+                proof_pubkey(signer.pubkey()),
+                proof.miner,
+                miner
+            )));
+        }
+
+        // Print the proof's authority and miner
+        println!("Proof: {}", proof_pubkey(signer.pubkey()));
+        println!("Authority: {}", signer.pubkey());
+        println!("Miner: {}", proof.miner);
+        Ok(())
+    }
+
+    /// Opens a proof for the signer, if needed, with the signer as its miner.
+    pub async fn register(&self) -> Result<()> {
+        self.open_proof(self.signer().pubkey()).await
+    }
+
+    /// Opens a proof for the signer, if needed, that the given miner is allowed to hash for.
+    async fn open_proof(&self, miner: Pubkey) -> Result<()> {
         // Return early if miner is already registered
         let signer = self.signer();
         let proof_address = proof_pubkey(signer.pubkey());
         if self.rpc_client.get_account(&proof_address).await.is_ok() {
-            return;
+            return Ok(());
         }
 
         // Sign and send transaction.
-        println!("Generating challenge...");
-        let ix = ore_api::instruction::open(signer.pubkey(), signer.pubkey(), signer.pubkey());
+        self.print("Generating challenge...".into());
+        let fee_payer = self.fee_payer();
+        let ix = ore_api::instruction::open(signer.pubkey(), miner, fee_payer.pubkey());
         self.send_and_confirm(&[ix], ComputeBudget::Dynamic, false)
-            .await
-            .ok();
+            .await?;
+        Ok(())
     }
 }
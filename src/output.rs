@@ -0,0 +1,93 @@
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::{
+    error::{OreCliError, Result},
+    utils::amount_u64_to_string,
+    Miner,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human readable text.
+    Text,
+
+    /// A JSON document.
+    Json,
+
+    /// A header row followed by one row per record.
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(format!("expected text, json, or csv, got {}", s)),
+        }
+    }
+}
+
+/// The result of a query command, printable in every output format.
+pub trait Report: Serialize {
+    /// The human readable form of the report.
+    fn text(&self) -> String;
+
+    /// The column names of the csv form of the report.
+    fn csv_header(&self) -> Vec<&'static str>;
+
+    /// The rows of the csv form of the report.
+    fn csv_rows(&self) -> Vec<Vec<String>>;
+}
+
+/// An amount of ORE, in base units and as an exact decimal.
+#[derive(Serialize)]
+pub struct TokenAmount {
+    pub amount: u64,
+    pub ui_amount: String,
+}
+
+impl TokenAmount {
+    pub fn new(amount: u64) -> Self {
+        Self {
+            amount,
+            ui_amount: amount_u64_to_string(amount),
+        }
+    }
+}
+
+impl Miner {
+    /// Prints a report in the configured output format.
+    pub fn print_report<T: Report>(&self, report: &T) -> Result<()> {
+        match self.output {
+            OutputFormat::Text => println!("{}", report.text()),
+            OutputFormat::Json => println!(
+                "{}",
+                serde_json::to_string_pretty(report)
+                    .map_err(|err| OreCliError::Parse(err.to_string()))?
+            ),
+            OutputFormat::Csv => {
+                println!("{}", report.csv_header().join(","));
+                for row in report.csv_rows() {
+                    let row: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+                    println!("{}", row.join(","));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Quotes a csv field, if it contains a delimiter, quote, or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,80 @@
+use std::str::FromStr;
+
+use colored::*;
+use solana_program::{instruction::Instruction, pubkey::Pubkey};
+
+use crate::Miner;
+
+#[derive(Clone, Copy, Debug)]
+pub enum PriorityFee {
+    Auto,
+    Fixed(u64),
+}
+
+impl FromStr for PriorityFee {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(PriorityFee::Auto);
+        }
+        u64::from_str(s)
+            .map(PriorityFee::Fixed)
+            .map_err(|_| format!("expected a number of microlamports or \"auto\", got {}", s))
+    }
+}
+
+impl Miner {
+    /// Returns the compute unit price to pay for a transaction with the given instructions.
+    pub async fn get_priority_fee(&self, ixs: &[Instruction]) -> u64 {
+        match self.priority_fee {
+            PriorityFee::Fixed(fee) => fee,
+            PriorityFee::Auto => {
+                // Fetch recent fees paid to write the accounts this transaction locks
+                let accounts = writable_accounts(ixs);
+                match self
+                    .rpc_client
+                    .get_recent_prioritization_fees(&accounts)
+                    .await
+                {
+                    Ok(recent_fees) => {
+                        let fees = recent_fees
+                            .iter()
+                            .map(|f| f.prioritization_fee)
+                            .collect::<Vec<_>>();
+                        percentile(fees, self.priority_fee_percentile).min(self.max_priority_fee)
+                    }
+                    Err(err) => {
+                        self.print(format!(
+                            "{} Failed to estimate priority fee, using max of {} microlamports: {}",
+                            "WARNING".bold().yellow(),
+                            self.max_priority_fee,
+                            err
+                        ));
+                        self.max_priority_fee
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Collects the accounts written by the instructions, excluding signers.
+fn writable_accounts(ixs: &[Instruction]) -> Vec<Pubkey> {
+    let mut accounts: Vec<Pubkey> = vec![];
+    for meta in ixs.iter().flat_map(|ix| ix.accounts.iter()) {
+        if meta.is_writable && !meta.is_signer && !accounts.contains(&meta.pubkey) {
+            accounts.push(meta.pubkey);
+        }
+    }
+    accounts
+}
+
+fn percentile(mut fees: Vec<u64>, percentile: u8) -> u64 {
+    if fees.is_empty() {
+        return 0;
+    }
+    fees.sort_unstable();
+    let index = (fees.len() - 1) * (percentile.min(100) as usize) / 100;
+    fees[index]
+}
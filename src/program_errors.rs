@@ -0,0 +1,197 @@
+use std::fmt;
+
+use num_traits::FromPrimitive;
+use ore_api::{error::OreError, instruction::OreInstruction};
+use solana_program::{
+    instruction::{Instruction, InstructionError},
+    system_instruction::SystemError,
+    system_program,
+};
+use solana_sdk::{compute_budget, transaction::TransactionError};
+use spl_token::error::TokenError;
+
+/// A program error the cli knows how to explain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KnownError {
+    Ore(OreError),
+    Token(TokenError),
+    System(SystemError),
+}
+
+/// The instruction that caused a transaction to fail.
+#[derive(Clone, Debug)]
+pub struct FailedInstruction {
+    pub index: u8,
+    pub name: &'static str,
+    pub known: Option<KnownError>,
+}
+
+impl KnownError {
+    /// A plain explanation of what went wrong and what to do about it.
+    pub fn explanation(&self) -> &'static str {
+        match self {
+            KnownError::Ore(err) => match err {
+                OreError::NeedsReset => {
+                    "The epoch has ended and the program needs a reset before more hashes are accepted."
+                }
+                OreError::HashInvalid => {
+                    "The hash does not match the proof's current challenge, most likely because another hash already landed."
+                }
+                OreError::HashTooEasy => {
+                    "The hash difficulty is below the program's minimum difficulty."
+                }
+                OreError::ClaimTooLarge => {
+                    "The claim amount is greater than the proof's balance."
+                }
+                OreError::ClockInvalid => "The on-chain clock is invalid.",
+                OreError::Spam => {
+                    "The hash was submitted too soon after the proof's last hash. Decrease the buffer time, or wait for the cutoff before submitting."
+                }
+                OreError::TransactionInvalid => {
+                    "Only one mine instruction may be included per transaction."
+                }
+                OreError::ToleranceOverflow => "The liveness tolerance overflowed.",
+                OreError::MaxSupply => "The maximum ORE supply has been reached.",
+                OreError::CannotClose => {
+                    "The proof cannot be closed while it is the top staker."
+                }
+                OreError::CannotCrown => {
+                    "The proof cannot be crowned because its last stake was too recent."
+                }
+            },
+            KnownError::Token(err) => match err {
+                TokenError::InsufficientFunds => {
+                    "The token account does not hold enough tokens for this transfer."
+                }
+                TokenError::MintMismatch => "The token account belongs to a different mint.",
+                TokenError::OwnerMismatch => {
+                    "The token account is not owned by the signer."
+                }
+                TokenError::AccountFrozen => "The token account is frozen.",
+                TokenError::NonNativeHasBalance => {
+                    "The token account cannot be closed while it holds tokens."
+                }
+                _ => "The token program rejected the instruction.",
+            },
+            KnownError::System(err) => match err {
+                SystemError::AccountAlreadyInUse => "The account already exists.",
+                SystemError::ResultWithNegativeLamports => {
+                    "The fee payer does not have enough SOL to cover this transfer."
+                }
+                _ => "The system program rejected the instruction.",
+            },
+        }
+    }
+}
+
+impl fmt::Display for KnownError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            KnownError::Ore(err) => match err {
+                OreError::NeedsReset => "needs reset",
+                OreError::HashInvalid => "hash invalid",
+                OreError::HashTooEasy => "hash too easy",
+                OreError::ClaimTooLarge => "claim too large",
+                OreError::ClockInvalid => "clock invalid",
+                OreError::Spam => "spam",
+                OreError::TransactionInvalid => "transaction invalid",
+                OreError::ToleranceOverflow => "tolerance overflow",
+                OreError::MaxSupply => "max supply",
+                OreError::CannotClose => "cannot close",
+                OreError::CannotCrown => "cannot crown",
+            },
+            KnownError::Token(_) => "token error",
+            KnownError::System(_) => "system error",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for FailedInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} instruction (#{}) failed", self.name, self.index)?;
+        if let Some(known) = &self.known {
+            write!(f, ": {}. {}", known, known.explanation())?;
+        }
+        Ok(())
+    }
+}
+
+/// Maps a transaction error back to the instruction that caused it.
+pub fn decode_transaction_error(
+    err: &TransactionError,
+    ixs: &[Instruction],
+) -> Option<FailedInstruction> {
+    let TransactionError::InstructionError(index, ix_err) = err else {
+        return None;
+    };
+    let ix = ixs.get(*index as usize)?;
+    let name = instruction_name(ix);
+    let known = if ix.program_id == ore_api::ID {
+        match ix_err {
+            InstructionError::Custom(code) => ore_error(*code).map(KnownError::Ore),
+            _ => None,
+        }
+    } else if ix.program_id == spl_token::id() {
+        match ix_err {
+            InstructionError::Custom(code) => TokenError::from_u32(*code).map(KnownError::Token),
+            _ => None,
+        }
+    } else if ix.program_id == system_program::id() {
+        match ix_err {
+            InstructionError::Custom(code) => SystemError::from_u32(*code).map(KnownError::System),
+            _ => None,
+        }
+    } else {
+        None
+    };
+    Some(FailedInstruction {
+        index: *index,
+        name,
+        known,
+    })
+}
+
+fn instruction_name(ix: &Instruction) -> &'static str {
+    if ix.program_id == ore_api::ID {
+        match ix.data.first().map(|d| OreInstruction::try_from(*d)) {
+            Some(Ok(OreInstruction::Claim)) => "claim",
+            Some(Ok(OreInstruction::Close)) => "close",
+            Some(Ok(OreInstruction::Crown)) => "crown",
+            Some(Ok(OreInstruction::Mine)) => "mine",
+            Some(Ok(OreInstruction::Open)) => "open",
+            Some(Ok(OreInstruction::Reset)) => "reset",
+            Some(Ok(OreInstruction::Stake)) => "stake",
+            Some(Ok(OreInstruction::Update)) => "update",
+            Some(Ok(OreInstruction::Upgrade)) => "upgrade",
+            _ => "ore",
+        }
+    } else if ix.program_id == compute_budget::id() {
+        "compute budget"
+    } else if ix.program_id == spl_associated_token_account::id() {
+        "create token account"
+    } else if ix.program_id == spl_token::id() {
+        "token"
+    } else if ix.program_id == system_program::id() {
+        "system"
+    } else {
+        "unknown"
+    }
+}
+
+fn ore_error(code: u32) -> Option<OreError> {
+    match code {
+        0 => Some(OreError::NeedsReset),
+        1 => Some(OreError::HashInvalid),
+        2 => Some(OreError::HashTooEasy),
+        3 => Some(OreError::ClaimTooLarge),
+        4 => Some(OreError::ClockInvalid),
+        5 => Some(OreError::Spam),
+        6 => Some(OreError::TransactionInvalid),
+        7 => Some(OreError::ToleranceOverflow),
+        8 => Some(OreError::MaxSupply),
+        9 => Some(OreError::CannotClose),
+        10 => Some(OreError::CannotCrown),
+        _ => None,
+    }
+}
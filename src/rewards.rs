@@ -1,29 +1,60 @@
+use serde::Serialize;
+
 use crate::{
-    utils::{amount_u64_to_string, get_config},
+    error::Result,
+    output::{Report, TokenAmount},
+    utils::get_config,
     Miner,
 };
 
+#[derive(Serialize)]
+pub struct RewardRate {
+    pub difficulty: u32,
+    pub reward: TokenAmount,
+}
+
+#[derive(Serialize)]
+pub struct RewardsReport(pub Vec<RewardRate>);
+
+impl Report for RewardsReport {
+    fn text(&self) -> String {
+        self.0
+            .iter()
+            .map(|rate| format!("{}: {} ORE", rate.difficulty, rate.reward.ui_amount))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn csv_header(&self) -> Vec<&'static str> {
+        vec!["difficulty", "reward", "reward_ui"]
+    }
+
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        self.0
+            .iter()
+            .map(|rate| {
+                vec![
+                    rate.difficulty.to_string(),
+                    rate.reward.amount.to_string(),
+                    rate.reward.ui_amount.clone(),
+                ]
+            })
+            .collect()
+    }
+}
+
 impl Miner {
-    pub async fn rewards(&self) {
-        let config = get_config(&self.rpc_client).await;
+    pub async fn rewards(&self) -> Result<()> {
+        let config = get_config(&self.rpc_client).await?;
         let base_reward_rate = config.base_reward_rate;
         let base_difficulty = ore_api::consts::MIN_DIFFICULTY;
 
-        let mut s = format!(
-            "{}: {} ORE",
-            base_difficulty,
-            amount_u64_to_string(base_reward_rate)
-        )
-        .to_string();
-        for i in 1..32 {
-            let reward_rate = base_reward_rate.saturating_mul(2u64.saturating_pow(i));
-            s = format!(
-                "{}\n{}: {} ORE",
-                s,
-                base_difficulty + i,
-                amount_u64_to_string(reward_rate)
-            );
-        }
-        println!("{}", s);
+        let rates = (0..32)
+            .map(|i| RewardRate {
+                difficulty: base_difficulty + i,
+                reward: TokenAmount::new(base_reward_rate.saturating_mul(2u64.saturating_pow(i))),
+            })
+            .collect();
+        self.print_report(&RewardsReport(rates))
     }
 }
@@ -2,14 +2,10 @@ use std::time::Duration;
 
 use colored::*;
 use solana_client::{
-    client_error::{ClientError, ClientErrorKind, Result as ClientResult},
-    rpc_config::RpcSendTransactionConfig,
+    client_error::{ClientError, ClientErrorKind},
+    rpc_config::{RpcSendTransactionConfig, RpcSimulateTransactionConfig},
 };
-use solana_program::{
-    instruction::Instruction,
-    native_token::{lamports_to_sol, sol_to_lamports},
-};
-use solana_rpc_client::spinner;
+use solana_program::{instruction::Instruction, native_token::sol_to_lamports, pubkey::Pubkey};
 use solana_sdk::{
     commitment_config::CommitmentLevel,
     compute_budget::ComputeBudgetInstruction,
@@ -18,17 +14,27 @@ use solana_sdk::{
 };
 use solana_transaction_status::{TransactionConfirmationStatus, UiTransactionEncoding};
 
-use crate::Miner;
+use crate::{
+    error::{OreCliError, Result},
+    program_errors::decode_transaction_error,
+    Miner,
+};
 
 const MIN_SOL_BALANCE: f64 = 0.005;
 
 const RPC_RETRIES: usize = 0;
-const _SIMULATION_RETRIES: usize = 4;
+const SIMULATION_RETRIES: usize = 4;
 const GATEWAY_RETRIES: usize = 150;
 const CONFIRM_RETRIES: usize = 1;
 
 const CONFIRM_DELAY: u64 = 0;
+const SIMULATION_DELAY: u64 = 500;
 const GATEWAY_DELAY: u64 = 300;
+const BLOCKHASH_CHECK_INTERVAL: usize = 10;
+
+const MAX_COMPUTE_UNITS: u32 = 1_400_000;
+const SIMULATION_CU_BUFFER_PERCENT: u32 = 10;
+const SIMULATION_CU_BUFFER_MIN: u32 = 1_000;
 
 pub enum ComputeBudget {
     Dynamic,
@@ -41,36 +47,63 @@ impl Miner {
         ixs: &[Instruction],
         compute_budget: ComputeBudget,
         skip_confirm: bool,
-    ) -> ClientResult<Signature> {
-        let progress_bar = spinner::new_progress_bar();
+    ) -> Result<Signature> {
+        let priority_fee = self.get_priority_fee(ixs).await;
+        self.send_and_confirm_with_priority_fee(ixs, compute_budget, skip_confirm, priority_fee)
+            .await
+    }
+
+    pub async fn send_and_confirm_with_priority_fee(
+        &self,
+        ixs: &[Instruction],
+        compute_budget: ComputeBudget,
+        skip_confirm: bool,
+        priority_fee: u64,
+    ) -> Result<Signature> {
+        let progress_bar = self.progress_bar();
         let signer = self.signer();
+        let fee_payer = self.fee_payer();
         let client = self.rpc_client.clone();
 
         // Return error, if balance is zero
-        if let Ok(balance) = client.get_balance(&signer.pubkey()).await {
+        if let Ok(balance) = client.get_balance(&fee_payer.pubkey()).await {
+            self.metrics.set_sol_balance(fee_payer.pubkey(), balance);
+            self.dashboard.set_sol_balance(balance);
             if balance <= sol_to_lamports(MIN_SOL_BALANCE) {
-                panic!(
-                    "{} Insufficient balance: {} SOL\nPlease top up with at least {} SOL",
-                    "ERROR".bold().red(),
-                    lamports_to_sol(balance),
-                    MIN_SOL_BALANCE
-                );
+                progress_bar.finish_and_clear();
+                return Err(OreCliError::InsufficientBalance {
+                    balance,
+                    required: sol_to_lamports(MIN_SOL_BALANCE),
+                });
             }
         }
 
         // Set compute units
         let mut final_ixs = vec![];
-        match compute_budget {
+        let compute_unit_limit = match compute_budget {
             ComputeBudget::Dynamic => {
-                // TODO simulate
-                final_ixs.push(ComputeBudgetInstruction::set_compute_unit_limit(1_400_000))
-            }
-            ComputeBudget::Fixed(cus) => {
-                final_ixs.push(ComputeBudgetInstruction::set_compute_unit_limit(cus))
+                progress_bar.set_message("Simulating transaction...");
+                match self
+                    .simulate_compute_units(ixs, priority_fee, &fee_payer.pubkey())
+                    .await
+                {
+                    Ok(cus) => cus,
+                    Err(err) => {
+                        progress_bar.finish_with_message(format!(
+                            "{}: Simulation failed",
+                            "ERROR".bold().red(),
+                        ));
+                        return Err(err);
+                    }
+                }
             }
-        }
+            ComputeBudget::Fixed(cus) => cus,
+        };
+        final_ixs.push(ComputeBudgetInstruction::set_compute_unit_limit(
+            compute_unit_limit,
+        ));
         final_ixs.push(ComputeBudgetInstruction::set_compute_unit_price(
-            self.priority_fee,
+            priority_fee,
         ));
         final_ixs.extend_from_slice(ixs);
 
@@ -82,19 +115,41 @@ impl Miner {
             max_retries: Some(RPC_RETRIES),
             min_context_slot: None,
         };
-        let mut tx = Transaction::new_with_payer(&final_ixs, Some(&signer.pubkey()));
+        let mut tx = Transaction::new_with_payer(&final_ixs, Some(&fee_payer.pubkey()));
+
+        // Sign with the fee payer, and the signer if the instructions need it
+        let signer_keys = tx.message.signer_keys();
+        let mut signers = vec![&fee_payer];
+        if signer.pubkey().ne(&fee_payer.pubkey()) && signer_keys.contains(&&signer.pubkey()) {
+            signers.push(&signer);
+        }
 
         // Sign tx
-        let (hash, _slot) = client
+        let (hash, mut last_valid_block_height) = match client
             .get_latest_blockhash_with_commitment(self.rpc_client.commitment())
             .await
-            .unwrap();
-        tx.sign(&[&signer], hash);
+        {
+            Ok(res) => res,
+            Err(err) => {
+                progress_bar.finish_with_message(format!(
+                    "{}: {}",
+                    "ERROR".bold().red(),
+                    err.kind()
+                ));
+                return Err(err.into());
+            }
+        };
+        tx.sign(&signers, hash);
 
         // Submit tx
         let mut attempts = 0;
+        let mut resigns = 0;
+        let mut sigs: Vec<Signature> = vec![];
         loop {
-            progress_bar.set_message(format!("Submitting transaction... (attempt {})", attempts));
+            progress_bar.set_message(format!(
+                "Submitting transaction... (attempt {}, re-signed {})",
+                attempts, resigns
+            ));
             match client.send_transaction_with_config(&tx, send_cfg).await {
                 Ok(sig) => {
                     // Skip confirmation
@@ -103,36 +158,55 @@ impl Miner {
                         return Ok(sig);
                     }
 
+                    // Track every signature sent, since an earlier blockhash may still land
+                    if !sigs.contains(&sig) {
+                        sigs.push(sig);
+                    }
+
                     // Confirm the tx landed
                     for _ in 0..CONFIRM_RETRIES {
-                        std::thread::sleep(Duration::from_millis(CONFIRM_DELAY));
-                        match client.get_signature_statuses(&[sig]).await {
+                        tokio::time::sleep(Duration::from_millis(CONFIRM_DELAY)).await;
+                        match client.get_signature_statuses(&sigs).await {
                             Ok(signature_statuses) => {
-                                for status in signature_statuses.value {
-                                    if let Some(status) = status {
-                                        if let Some(err) = status.err {
-                                            progress_bar.finish_with_message(format!(
-                                                "{}: {}",
-                                                "ERROR".bold().red(),
-                                                err
-                                            ));
-                                            return Err(ClientError {
-                                                request: None,
-                                                kind: ClientErrorKind::Custom(err.to_string()),
-                                            });
-                                        }
-                                        if let Some(confirmation) = status.confirmation_status {
-                                            match confirmation {
-                                                TransactionConfirmationStatus::Processed => {}
-                                                TransactionConfirmationStatus::Confirmed
-                                                | TransactionConfirmationStatus::Finalized => {
-                                                    progress_bar.finish_with_message(format!(
-                                                        "{} {}",
-                                                        "OK".bold().green(),
-                                                        sig
-                                                    ));
-                                                    return Ok(sig);
-                                                }
+                                for (sig, status) in sigs
+                                    .iter()
+                                    .zip(signature_statuses.value)
+                                    .filter_map(|(sig, status)| Some((*sig, status?)))
+                                {
+                                    if let Some(err) = status.err {
+                                        // Failed transactions still pay the priority fee
+                                        self.metrics
+                                            .record_priority_fee(priority_fee, compute_unit_limit);
+                                        let failed = decode_transaction_error(&err, &final_ixs);
+                                        progress_bar.finish_with_message(format!(
+                                            "{}: {}",
+                                            "ERROR".bold().red(),
+                                            failed
+                                                .as_ref()
+                                                .map_or(err.to_string(), |f| f.to_string())
+                                        ));
+                                        return Err(OreCliError::Program {
+                                            err,
+                                            failed,
+                                            logs: vec![],
+                                        });
+                                    }
+                                    if let Some(confirmation) = status.confirmation_status {
+                                        match confirmation {
+                                            TransactionConfirmationStatus::Processed => {}
+                                            TransactionConfirmationStatus::Confirmed
+                                            | TransactionConfirmationStatus::Finalized => {
+                                                self.metrics.record_priority_fee(
+                                                    priority_fee,
+                                                    compute_unit_limit,
+                                                );
+                                                progress_bar.finish_with_message(format!(
+                                                    "{} {}{}",
+                                                    "OK".bold().green(),
+                                                    sig,
+                                                    resigns_note(resigns)
+                                                ));
+                                                return Ok(sig);
                                             }
                                         }
                                     }
@@ -144,7 +218,7 @@ impl Miner {
                                 progress_bar.set_message(format!(
                                     "{}: {}",
                                     "ERROR".bold().red(),
-                                    err.kind().to_string()
+                                    err.kind()
                                 ));
                             }
                         }
@@ -153,81 +227,119 @@ impl Miner {
 
                 // Handle submit errors
                 Err(err) => {
-                    progress_bar.set_message(format!(
-                        "{}: {}",
-                        "ERROR".bold().red(),
-                        err.kind().to_string()
-                    ));
+                    progress_bar.set_message(format!("{}: {}", "ERROR".bold().red(), err.kind()));
+                }
+            }
+
+            // Re-sign with a fresh blockhash, if the current one has expired
+            if attempts > 0 && attempts % BLOCKHASH_CHECK_INTERVAL == 0 {
+                if let Ok(block_height) = client.get_block_height().await {
+                    if block_height > last_valid_block_height {
+                        if let Ok((hash, block_height)) = client
+                            .get_latest_blockhash_with_commitment(self.rpc_client.commitment())
+                            .await
+                        {
+                            tx.sign(&signers, hash);
+                            last_valid_block_height = block_height;
+                            resigns += 1;
+                        }
+                    }
                 }
             }
 
             // Retry
-            std::thread::sleep(Duration::from_millis(GATEWAY_DELAY));
+            tokio::time::sleep(Duration::from_millis(GATEWAY_DELAY)).await;
             attempts += 1;
             if attempts > GATEWAY_RETRIES {
-                progress_bar.finish_with_message(format!("{}: Max retries", "ERROR".bold().red()));
-                return Err(ClientError {
-                    request: None,
-                    kind: ClientErrorKind::Custom("Max retries".into()),
-                });
+                progress_bar.finish_with_message(format!(
+                    "{}: Max retries{}",
+                    "ERROR".bold().red(),
+                    resigns_note(resigns)
+                ));
+                return Err(OreCliError::NotLanded { attempts });
             }
         }
     }
 
-    // TODO
-    fn _simulate(&self) {
+    /// Simulates the instructions, returning the compute units they need or the error they would
+    /// fail with.
+    pub async fn simulate_compute_units(
+        &self,
+        ixs: &[Instruction],
+        priority_fee: u64,
+        payer: &Pubkey,
+    ) -> Result<u32> {
+        // Build simulation tx with the max compute budget
+        let mut sim_ixs = vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(MAX_COMPUTE_UNITS),
+            ComputeBudgetInstruction::set_compute_unit_price(priority_fee),
+        ];
+        sim_ixs.extend_from_slice(ixs);
+        let tx = Transaction::new_with_payer(&sim_ixs, Some(payer));
+        let sim_cfg = RpcSimulateTransactionConfig {
+            sig_verify: false,
+            replace_recent_blockhash: true,
+            commitment: Some(self.rpc_client.commitment()),
+            encoding: Some(UiTransactionEncoding::Base64),
+            accounts: None,
+            min_context_slot: None,
+            inner_instructions: false,
+        };
 
         // Simulate tx
-        // let mut sim_attempts = 0;
-        // 'simulate: loop {
-        //     let sim_res = client
-        //         .simulate_transaction_with_config(
-        //             &tx,
-        //             RpcSimulateTransactionConfig {
-        //                 sig_verify: false,
-        //                 replace_recent_blockhash: true,
-        //                 commitment: Some(self.rpc_client.commitment()),
-        //                 encoding: Some(UiTransactionEncoding::Base64),
-        //                 accounts: None,
-        //                 min_context_slot: Some(slot),
-        //                 inner_instructions: false,
-        //             },
-        //         )
-        //         .await;
-        //     match sim_res {
-        //         Ok(sim_res) => {
-        //             if let Some(err) = sim_res.value.err {
-        //                 println!("Simulaton error: {:?}", err);
-        //                 sim_attempts += 1;
-        //             } else if let Some(units_consumed) = sim_res.value.units_consumed {
-        //                 if dynamic_cus {
-        //                     println!("Dynamic CUs: {:?}", units_consumed);
-        //                     let cu_budget_ix = ComputeBudgetInstruction::set_compute_unit_limit(
-        //                         units_consumed as u32 + 1000,
-        //                     );
-        //                     let cu_price_ix =
-        //                         ComputeBudgetInstruction::set_compute_unit_price(self.priority_fee);
-        //                     let mut final_ixs = vec![];
-        //                     final_ixs.extend_from_slice(&[cu_budget_ix, cu_price_ix]);
-        //                     final_ixs.extend_from_slice(ixs);
-        //                     tx = Transaction::new_with_payer(&final_ixs, Some(&signer.pubkey()));
-        //                 }
-        //                 break 'simulate;
-        //             }
-        //         }
-        //         Err(err) => {
-        //             println!("Simulaton error: {:?}", err);
-        //             sim_attempts += 1;
-        //         }
-        //     }
-
-        //     // Abort if sim fails
-        //     if sim_attempts.gt(&SIMULATION_RETRIES) {
-        //         return Err(ClientError {
-        //             request: None,
-        //             kind: ClientErrorKind::Custom("Simulation failed".into()),
-        //         });
-        //     }
-        // }
+        let mut sim_attempts = 0;
+        loop {
+            match self
+                .rpc_client
+                .simulate_transaction_with_config(&tx, sim_cfg.clone())
+                .await
+            {
+                Ok(sim_res) => {
+                    // Abort early if the tx would fail
+                    if let Some(err) = sim_res.value.err {
+                        return Err(OreCliError::Program {
+                            failed: decode_transaction_error(&err, &sim_ixs),
+                            err,
+                            logs: sim_res.value.logs.unwrap_or_default(),
+                        });
+                    }
+
+                    // Add a safety margin to the consumed units
+                    if let Some(units_consumed) = sim_res.value.units_consumed {
+                        let units_consumed = units_consumed as u32;
+                        let buffer = (units_consumed / 100 * SIMULATION_CU_BUFFER_PERCENT)
+                            .max(SIMULATION_CU_BUFFER_MIN);
+                        return Ok(units_consumed.saturating_add(buffer).min(MAX_COMPUTE_UNITS));
+                    }
+                }
+
+                // Retry on rpc errors
+                Err(err) => {
+                    if sim_attempts >= SIMULATION_RETRIES {
+                        return Err(err.into());
+                    }
+                }
+            }
+
+            // Abort if sim fails
+            sim_attempts += 1;
+            if sim_attempts > SIMULATION_RETRIES {
+                return Err(OreCliError::Rpc(ClientError {
+                    request: None,
+                    kind: ClientErrorKind::Custom("Simulation failed".into()),
+                }));
+            }
+
+            // Back off before retrying
+            tokio::time::sleep(Duration::from_millis(SIMULATION_DELAY)).await;
+        }
+    }
+}
+
+fn resigns_note(resigns: usize) -> String {
+    if resigns > 0 {
+        format!(" (re-signed {} times)", resigns)
+    } else {
+        "".into()
     }
 }
@@ -1,32 +1,31 @@
 use colored::*;
 use solana_sdk::{pubkey::Pubkey, signer::Signer};
-use spl_token::amount_to_ui_amount;
 
 use crate::{
     cu_limits::CU_LIMIT_UPGRADE,
+    error::{OreCliError, Result},
     send_and_confirm::ComputeBudget,
-    utils::{amount_f64_to_u64_v1, ask_confirm},
+    utils::{amount_u64_to_string_v1, ask_confirm, get_token_balance},
     Miner, UpgradeArgs,
 };
 
 impl Miner {
-    pub async fn upgrade(&self, args: UpgradeArgs) {
+    pub async fn upgrade(&self, args: UpgradeArgs) -> Result<()> {
         let signer = &self.signer();
-        let beneficiary = self.get_or_initialize_ata().await;
-        let (sender, sender_balance) = self.get_ata_v1().await;
+        let beneficiary = self.get_or_initialize_ata().await?;
+        let (sender, sender_balance) = self.get_ata_v1().await?;
 
-        let amount_f64 = match args.amount {
-            Some(f64) => f64,
+        let amount = match args.amount {
+            Some(amount) => amount,
             None => {
                 println!(
                     "Defaulting to max amount of v1 Ore token in wallet: {}",
-                    sender_balance
+                    amount_u64_to_string_v1(sender_balance)
                 );
                 sender_balance
             }
         };
-        let amount = amount_f64_to_u64_v1(amount_f64);
-        let amount_ui = amount_to_ui_amount(amount, ore_api::consts::TOKEN_DECIMALS_V1);
+        let amount_ui = amount_u64_to_string_v1(amount);
 
         if !ask_confirm(
             format!(
@@ -34,24 +33,19 @@ impl Miner {
                 format!("{} ORE", amount_ui).bold(),
             )
             .as_str(),
+            self.assume_yes,
         ) {
-            return;
+            return Err(OreCliError::UserAbort);
         }
 
         let ix = ore_api::instruction::upgrade(signer.pubkey(), beneficiary, sender, amount);
-        match self
-            .send_and_confirm(&[ix], ComputeBudget::Fixed(CU_LIMIT_UPGRADE), false)
-            .await
-        {
-            Ok(_sig) => {}
-            Err(err) => {
-                println!("error: {}", err);
-            }
-        }
+        self.send_and_confirm(&[ix], ComputeBudget::Fixed(CU_LIMIT_UPGRADE), false)
+            .await?;
+        Ok(())
     }
 
     // asserts that token account exists and gets balance
-    async fn get_ata_v1(&self) -> (Pubkey, f64) {
+    async fn get_ata_v1(&self) -> Result<(Pubkey, u64)> {
         // Initialize client.
         let signer = self.signer();
         let client = self.rpc_client.clone();
@@ -63,29 +57,18 @@ impl Miner {
         );
 
         // Get token account balance
-        let balance = match client.get_token_account(&token_account_pubkey_v1).await {
-            Ok(None) => {
-                panic!("v1 token account doesn't exist")
-            }
-            Ok(Some(token_account)) => match token_account.token_amount.ui_amount {
-                Some(ui_amount) => ui_amount,
-                None => {
-                    panic!(
-                        "Error parsing token account UI amount: {}",
-                        token_account.token_amount.amount
-                    )
-                }
-            },
-            Err(err) => {
-                panic!("Error fetching token account: {}", err)
-            }
+        let Some(balance) = get_token_balance(&client, &token_account_pubkey_v1).await? else {
+            return Err(OreCliError::AccountNotFound(format!(
+                "v1 token account {}",
+                token_account_pubkey_v1
+            )));
         };
 
         // Return v1 token account address
-        (token_account_pubkey_v1, balance)
+        Ok((token_account_pubkey_v1, balance))
     }
 
-    async fn get_or_initialize_ata(&self) -> Pubkey {
+    async fn get_or_initialize_ata(&self) -> Result<Pubkey> {
         // Initialize client
         let signer = self.signer();
         let client = self.rpc_client.clone();
@@ -97,20 +80,22 @@ impl Miner {
         );
 
         // Check if ata already exists or init
-        if let Err(_err) = client.get_token_account(&token_account_pubkey).await {
+        if get_token_balance(&client, &token_account_pubkey)
+            .await?
+            .is_none()
+        {
             println!("Initializing v2 token account...");
             let ix = spl_associated_token_account::instruction::create_associated_token_account(
-                &signer.pubkey(),
+                &self.fee_payer().pubkey(),
                 &signer.pubkey(),
                 &ore_api::consts::MINT_ADDRESS,
                 &spl_token::id(),
             );
             self.send_and_confirm(&[ix], ComputeBudget::Dynamic, false)
-                .await
-                .ok();
+                .await?;
         }
 
         // Return token account address
-        token_account_pubkey
+        Ok(token_account_pubkey)
     }
 }
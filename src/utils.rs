@@ -1,4 +1,4 @@
-use std::io::Read;
+use std::{io::IsTerminal, str::FromStr};
 
 use cached::proc_macro::cached;
 use ore_api::{
@@ -9,71 +9,154 @@ use ore_api::{
 };
 use ore_utils::AccountDeserialize;
 use solana_client::nonblocking::rpc_client::RpcClient;
-use solana_program::{pubkey::Pubkey, sysvar};
+use solana_program::{program_pack::Pack, pubkey::Pubkey, sysvar};
 use solana_sdk::clock::Clock;
 use spl_associated_token_account::get_associated_token_address;
 
-pub async fn _get_treasury(client: &RpcClient) -> Treasury {
-    let data = client
-        .get_account_data(&TREASURY_ADDRESS)
-        .await
-        .expect("Failed to get treasury account");
-    *Treasury::try_from_bytes(&data).expect("Failed to parse treasury account")
+use crate::error::{OreCliError, Result};
+
+pub async fn _get_treasury(client: &RpcClient) -> Result<Treasury> {
+    let data = client.get_account_data(&TREASURY_ADDRESS).await?;
+    Treasury::try_from_bytes(&data)
+        .copied()
+        .map_err(|_| OreCliError::Parse("Failed to parse treasury account".into()))
 }
 
-pub async fn get_config(client: &RpcClient) -> Config {
-    let data = client
-        .get_account_data(&CONFIG_ADDRESS)
-        .await
-        .expect("Failed to get config account");
-    *Config::try_from_bytes(&data).expect("Failed to parse config account")
+pub async fn get_config(client: &RpcClient) -> Result<Config> {
+    let data = client.get_account_data(&CONFIG_ADDRESS).await?;
+    Config::try_from_bytes(&data)
+        .copied()
+        .map_err(|_| OreCliError::Parse("Failed to parse config account".into()))
 }
 
-pub async fn get_proof_with_authority(client: &RpcClient, authority: Pubkey) -> Proof {
+pub async fn get_proof_with_authority(client: &RpcClient, authority: Pubkey) -> Result<Proof> {
     let proof_address = proof_pubkey(authority);
     get_proof(client, proof_address).await
 }
 
-pub async fn get_proof(client: &RpcClient, address: Pubkey) -> Proof {
-    let data = client
-        .get_account_data(&address)
-        .await
-        .expect("Failed to get miner account");
-    *Proof::try_from_bytes(&data).expect("Failed to parse miner account")
+pub async fn get_proof(client: &RpcClient, address: Pubkey) -> Result<Proof> {
+    let data = client.get_account_data(&address).await?;
+    Proof::try_from_bytes(&data)
+        .copied()
+        .map_err(|_| OreCliError::Parse("Failed to parse miner account".into()))
+}
+
+/// Fetches the balance of a token account, or None if the account does not exist. Unlike
+/// `RpcClient::get_token_account`, rpc failures are not reported as a missing account.
+pub async fn get_token_balance(client: &RpcClient, address: &Pubkey) -> Result<Option<u64>> {
+    let Some(account) = client
+        .get_account_with_commitment(address, client.commitment())
+        .await?
+        .value
+    else {
+        return Ok(None);
+    };
+    spl_token::state::Account::unpack(&account.data)
+        .map(|token_account| Some(token_account.amount))
+        .map_err(|_| OreCliError::Parse(format!("Failed to parse token account {}", address)))
+}
+
+pub async fn get_clock(client: &RpcClient) -> Result<Clock> {
+    let data = client.get_account_data(&sysvar::clock::ID).await?;
+    bincode::deserialize::<Clock>(&data)
+        .map_err(|_| OreCliError::Parse("Failed to deserialize clock".into()))
 }
 
-pub async fn get_clock(client: &RpcClient) -> Clock {
-    let data = client
-        .get_account_data(&sysvar::clock::ID)
-        .await
-        .expect("Failed to get miner account");
-    bincode::deserialize::<Clock>(&data).expect("Failed to deserialize clock")
+pub fn parse_pubkey(address: &str) -> Result<Pubkey> {
+    Pubkey::from_str(address)
+        .map_err(|_| OreCliError::InvalidArgs(format!("Invalid address: {}", address)))
 }
 
 pub fn amount_u64_to_string(amount: u64) -> String {
-    amount_u64_to_f64(amount).to_string()
+    format_decimal_amount(amount, TOKEN_DECIMALS)
+}
+
+pub fn amount_u64_to_string_v1(amount: u64) -> String {
+    format_decimal_amount(amount, TOKEN_DECIMALS_V1)
+}
+
+/// Parses a decimal amount of ORE into base units.
+pub fn parse_amount(s: &str) -> std::result::Result<u64, String> {
+    parse_decimal_amount(s, TOKEN_DECIMALS)
 }
 
-pub fn amount_u64_to_f64(amount: u64) -> f64 {
-    (amount as f64) / 10f64.powf(TOKEN_DECIMALS as f64)
+/// Parses a decimal amount of v1 ORE into base units.
+pub fn parse_amount_v1(s: &str) -> std::result::Result<u64, String> {
+    parse_decimal_amount(s, TOKEN_DECIMALS_V1)
 }
 
-pub fn amount_f64_to_u64(amount: f64) -> u64 {
-    (amount * 10f64.powf(TOKEN_DECIMALS as f64)) as u64
+/// Parses a decimal string into base units of a token with the given decimals, without going
+/// through floats. Rejects amounts more precise than the token, or too large for a u64.
+pub fn parse_decimal_amount(s: &str, decimals: u8) -> std::result::Result<u64, String> {
+    let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+    if (whole.is_empty() && fraction.is_empty())
+        || !whole.chars().all(|c| c.is_ascii_digit())
+        || !fraction.chars().all(|c| c.is_ascii_digit())
+    {
+        return Err(format!("invalid amount: {}", s));
+    }
+
+    // Trailing zeros past the token's precision don't change the amount
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.len() > decimals as usize {
+        return Err(format!(
+            "amount {} has more than {} decimal places",
+            s, decimals
+        ));
+    }
+
+    // Combine whole and fractional base units
+    let too_large = || format!("amount is too large: {}", s);
+    let whole = match whole.trim_start_matches('0') {
+        "" => 0,
+        digits => digits.parse::<u64>().map_err(|_| too_large())?,
+    };
+    let fraction = format!("{:0<width$}", fraction, width = decimals as usize);
+    let fraction = match fraction.as_str() {
+        "" => 0,
+        digits => digits.parse::<u64>().map_err(|_| too_large())?,
+    };
+    whole
+        .checked_mul(10u64.pow(decimals as u32))
+        .and_then(|whole| whole.checked_add(fraction))
+        .ok_or_else(too_large)
 }
 
-pub fn amount_f64_to_u64_v1(amount: f64) -> u64 {
-    (amount * 10f64.powf(TOKEN_DECIMALS_V1 as f64)) as u64
+/// Formats base units of a token with the given decimals as an exact decimal string, without
+/// trailing zeros.
+pub fn format_decimal_amount(amount: u64, decimals: u8) -> String {
+    let unit = 10u64.pow(decimals as u32);
+    let whole = amount / unit;
+    let fraction = format!("{:0>width$}", amount % unit, width = decimals as usize);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        whole.to_string()
+    } else {
+        format!("{}.{}", whole, fraction)
+    }
 }
 
-pub fn ask_confirm(question: &str) -> bool {
+/// Asks the user to confirm, unless confirmation is assumed. Refuses when stdin is not a
+/// terminal or is closed, so unattended runs never wait on input.
+pub fn ask_confirm(question: &str, assume_yes: bool) -> bool {
     println!("{}", question);
+    if assume_yes {
+        return true;
+    }
+    let stdin = std::io::stdin();
+    if !stdin.is_terminal() {
+        println!("Refusing to continue without a terminal. Pass --yes to confirm.");
+        return false;
+    }
     loop {
-        let mut input = [0];
-        let _ = std::io::stdin().read(&mut input);
-        match input[0] as char {
-            'y' | 'Y' => return true,
-            'n' | 'N' => return false,
+        let mut input = String::new();
+        match stdin.read_line(&mut input) {
+            Ok(0) | Err(_) => return false,
+            Ok(_) => {}
+        }
+        match input.trim() {
+            "y" | "Y" => return true,
+            "n" | "N" => return false,
             _ => println!("y/n only please."),
         }
     }
@@ -88,3 +171,81 @@ pub fn proof_pubkey(authority: Pubkey) -> Pubkey {
 pub fn treasury_tokens_pubkey() -> Pubkey {
     get_associated_token_address(&TREASURY_ADDRESS, &MINT_ADDRESS)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const DECIMALS: [u8; 2] = [TOKEN_DECIMALS, TOKEN_DECIMALS_V1];
+
+    proptest! {
+        #[test]
+        fn amounts_round_trip(amount: u64) {
+            for decimals in DECIMALS {
+                let s = format_decimal_amount(amount, decimals);
+                prop_assert_eq!(parse_decimal_amount(&s, decimals), Ok(amount));
+            }
+        }
+
+        #[test]
+        fn parses_exact_base_units(whole in 0u64..10_000_000, fraction in "[0-9]{0,9}") {
+            for decimals in DECIMALS {
+                let s = format!("{}.{}", whole, fraction);
+                let padded = format!("{:0<width$}", fraction, width = decimals as usize);
+                let expected = whole * 10u64.pow(decimals as u32) + padded.parse::<u64>().unwrap();
+                prop_assert_eq!(parse_decimal_amount(&s, decimals), Ok(expected));
+            }
+        }
+
+        #[test]
+        fn rejects_over_precision(whole in 0u64..1_000, fraction in "[0-9]{0,11}", last in 1u8..10) {
+            for decimals in DECIMALS {
+                let fraction = format!("{:0<width$}{}", fraction, last, width = decimals as usize);
+                let s = format!("{}.{}", whole, fraction);
+                prop_assert!(parse_decimal_amount(&s, decimals).is_err());
+            }
+        }
+
+        #[test]
+        fn ignores_trailing_zeros(amount: u64, zeros in 1usize..8) {
+            for decimals in DECIMALS {
+                let s = format_decimal_amount(amount, decimals);
+                let s = if s.contains('.') { s } else { format!("{}.", s) };
+                let padded = format!("{}{}", s, "0".repeat(decimals as usize + zeros));
+                prop_assert_eq!(parse_decimal_amount(&padded, decimals), Ok(amount));
+            }
+        }
+
+        #[test]
+        fn rejects_non_decimal_input(s in "[^0-9.]+|[0-9]*\\.[0-9]*\\.[0-9]*") {
+            for decimals in DECIMALS {
+                prop_assert!(parse_decimal_amount(&s, decimals).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_overflow() {
+        for decimals in DECIMALS {
+            let s = format!("{}.1", u64::MAX / 10u64.pow(decimals as u32) + 1);
+            assert!(parse_decimal_amount(&s, decimals).is_err());
+        }
+    }
+
+    #[test]
+    fn parses_decimal_amounts() {
+        assert_eq!(parse_amount("0.1"), Ok(10_000_000_000));
+        assert_eq!(parse_amount("1"), Ok(100_000_000_000));
+        assert_eq!(parse_amount(".5"), Ok(50_000_000_000));
+        assert_eq!(parse_amount("2."), Ok(200_000_000_000));
+        assert_eq!(parse_amount("0.00000000001"), Ok(1));
+        assert!(parse_amount("0.000000000001").is_err());
+        assert_eq!(parse_amount_v1("0.1"), Ok(100_000_000));
+        assert!(parse_amount_v1("0.0000000001").is_err());
+        assert!(parse_amount("").is_err());
+        assert!(parse_amount(".").is_err());
+        assert!(parse_amount("-1").is_err());
+        assert!(parse_amount("1e3").is_err());
+    }
+}